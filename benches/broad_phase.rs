@@ -0,0 +1,72 @@
+//! Compares the spatial hash broad phase against testing every pair of
+//! colliders, for crowds of enemy-sized circles spread over the default arena.
+//!
+//! Run with `cargo bench --bench broad_phase`.
+
+use bevy::math::Vec2;
+use bevy_ball_game::spatial_hash::SpatialHash;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const ARENA_SIZE: Vec2 = Vec2::new(1280., 720.);
+const RADIUS: f32 = 32.;
+const CELL_SIZE: f32 = 64.;
+const COUNTS: [usize; 4] = [100, 500, 1000, 5000];
+
+fn positions(count: usize) -> Vec<Vec2> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..count)
+        .map(|_| Vec2::new(rng.gen::<f32>(), rng.gen::<f32>()) * ARENA_SIZE)
+        .collect()
+}
+
+fn touching(a: Vec2, b: Vec2) -> bool {
+    a.distance(b) <= RADIUS * 2.
+}
+
+fn brute_force(positions: &[Vec2]) -> usize {
+    let mut hits = 0;
+    for (index, &position) in positions.iter().enumerate() {
+        for &other_position in &positions[index + 1..] {
+            if touching(position, other_position) {
+                hits += 1;
+            }
+        }
+    }
+    hits
+}
+
+fn spatial_hash(grid: &mut SpatialHash<usize>, positions: &[Vec2]) -> usize {
+    grid.clear();
+    for (index, &position) in positions.iter().enumerate() {
+        grid.insert(index, position, Vec2::splat(RADIUS));
+    }
+    grid.candidate_pairs()
+        .into_iter()
+        .filter(|&(a, b)| touching(positions[a], positions[b]))
+        .count()
+}
+
+fn broad_phase(c: &mut Criterion) {
+    let mut group = c.benchmark_group("broad_phase");
+    for count in COUNTS {
+        let positions = positions(count);
+        let mut grid = SpatialHash::new(CELL_SIZE);
+        assert_eq!(brute_force(&positions), spatial_hash(&mut grid, &positions));
+
+        group.bench_with_input(
+            BenchmarkId::new("brute_force", count),
+            &positions,
+            |b, positions| b.iter(|| brute_force(black_box(positions))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("spatial_hash", count),
+            &positions,
+            |b, positions| b.iter(|| spatial_hash(&mut grid, black_box(positions))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, broad_phase);
+criterion_main!(benches);
@@ -1,6 +1,78 @@
 use bevy::prelude::*;
 
-#[derive(Event)]
-pub struct GameOver {
+use crate::game::audio::SoundId;
+use crate::game::power_ups::PowerUpKind;
+
+/// A new game started, or the current one was restarted.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RunStarted {
+    pub seed: u64,
+}
+
+/// The game is over, with the score it ended with.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RunEnded {
     pub score: u32,
 }
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerDied {
+    pub position: Vec2,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StarCollected {
+    pub position: Vec2,
+    /// Score after collecting the star.
+    pub score: u32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EnemySpawned {
+    pub entity: Entity,
+    pub position: Vec2,
+}
+
+/// An enemy bounced off a wall or another enemy. Two enemies bouncing off
+/// each other send one each.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EnemyBounced {
+    pub entity: Entity,
+    pub position: Vec2,
+    /// The enemy it bounced off, `None` for a wall.
+    pub other: Option<Entity>,
+}
+
+/// A wave of enemies started, counting from one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WaveStarted {
+    pub number: u32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WaveCleared {
+    pub number: u32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PowerUpCollected {
+    pub kind: PowerUpKind,
+    pub position: Vec2,
+}
+
+/// A power-up the player had running ran out of time.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PowerUpExpired {
+    pub kind: PowerUpKind,
+}
+
+/// The shield took an enemy hit in place of the player, and is gone.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShieldBroken {
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct PlaySound {
+    pub sound: SoundId,
+}
@@ -0,0 +1,505 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever the input map file format changes.
+pub const INPUT_MAP_VERSION: u32 = 1;
+pub const INPUT_MAP_FILE_NAME: &str = "input_map.ron";
+
+/// How far an action has to be pushed to count as pressed.
+pub const ACTION_PRESS_THRESHOLD: f32 = 0.5;
+/// Stick movement below this is ignored, so a worn stick does not drift.
+pub const AXIS_DEAD_ZONE: f32 = 0.2;
+
+/// Turns keys, gamepad buttons and sticks into [`Action`]s through the
+/// player's [`InputMap`], once per frame.
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<ActionState>()
+            .add_event::<InputMapChanged>()
+            .add_systems(PreUpdate, update_action_state.after(InputSystem));
+    }
+}
+
+/// Something the player can do, whatever device they do it with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Pause,
+    Confirm,
+    Back,
+    Ability,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::Ability,
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::Ability => "Ability",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// A key, gamepad button or one half of a gamepad axis. Gamepad bindings
+/// work on every connected gamepad.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    GamepadButton(GamepadButtonType),
+    GamepadAxis(GamepadAxisType, AxisDirection),
+}
+
+impl Binding {
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Binding::Key(_))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key_code) => write!(f, "{key_code:?}"),
+            Binding::GamepadButton(button_type) => write!(f, "Pad {button_type:?}"),
+            Binding::GamepadAxis(axis_type, AxisDirection::Positive) => {
+                write!(f, "Pad {axis_type:?}+")
+            }
+            Binding::GamepadAxis(axis_type, AxisDirection::Negative) => {
+                write!(f, "Pad {axis_type:?}-")
+            }
+        }
+    }
+}
+
+/// Which bindings trigger each action.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InputMap {
+    pub version: u32,
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::{GamepadAxis, GamepadButton, Key};
+
+        Self {
+            version: INPUT_MAP_VERSION,
+            bindings: BTreeMap::from([
+                (
+                    Action::MoveUp,
+                    vec![
+                        Key(KeyCode::W),
+                        Key(KeyCode::Up),
+                        GamepadButton(GamepadButtonType::DPadUp),
+                        GamepadAxis(GamepadAxisType::LeftStickY, AxisDirection::Positive),
+                    ],
+                ),
+                (
+                    Action::MoveDown,
+                    vec![
+                        Key(KeyCode::S),
+                        Key(KeyCode::Down),
+                        GamepadButton(GamepadButtonType::DPadDown),
+                        GamepadAxis(GamepadAxisType::LeftStickY, AxisDirection::Negative),
+                    ],
+                ),
+                (
+                    Action::MoveLeft,
+                    vec![
+                        Key(KeyCode::A),
+                        Key(KeyCode::Left),
+                        GamepadButton(GamepadButtonType::DPadLeft),
+                        GamepadAxis(GamepadAxisType::LeftStickX, AxisDirection::Negative),
+                    ],
+                ),
+                (
+                    Action::MoveRight,
+                    vec![
+                        Key(KeyCode::D),
+                        Key(KeyCode::Right),
+                        GamepadButton(GamepadButtonType::DPadRight),
+                        GamepadAxis(GamepadAxisType::LeftStickX, AxisDirection::Positive),
+                    ],
+                ),
+                (
+                    Action::Pause,
+                    vec![Key(KeyCode::Space), GamepadButton(GamepadButtonType::Start)],
+                ),
+                (
+                    Action::Confirm,
+                    vec![
+                        Key(KeyCode::Return),
+                        Key(KeyCode::NumpadEnter),
+                        GamepadButton(GamepadButtonType::South),
+                    ],
+                ),
+                (
+                    Action::Back,
+                    vec![Key(KeyCode::Escape), GamepadButton(GamepadButtonType::East)],
+                ),
+                (
+                    Action::Ability,
+                    vec![Key(KeyCode::E), GamepadButton(GamepadButtonType::West)],
+                ),
+            ]),
+        }
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Puts `binding` in place of the first binding of its device for
+    /// `action`, so rebinding a key leaves the other keys and the gamepad
+    /// bindings alone. Another action already bound to `binding` gets the
+    /// replaced binding instead, so no two actions share one.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if bindings.contains(&binding) {
+            return;
+        }
+        let replaced = match bindings
+            .iter()
+            .position(|existing| existing.is_keyboard() == binding.is_keyboard())
+        {
+            Some(index) => Some(std::mem::replace(&mut bindings[index], binding)),
+            None => {
+                bindings.insert(0, binding);
+                None
+            }
+        };
+
+        for (_, other_bindings) in self
+            .bindings
+            .iter_mut()
+            .filter(|(other_action, _)| **other_action != action)
+        {
+            if let Some(index) = other_bindings.iter().position(|other| *other == binding) {
+                match replaced {
+                    Some(replaced) if !other_bindings.contains(&replaced) => {
+                        other_bindings[index] = replaced
+                    }
+                    _ => {
+                        other_bindings.remove(index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the input map from a file. A missing file is the default map.
+    pub fn read(path: &Path) -> Result<Self, InputMapError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(error) => return Err(InputMapError::Io(error)),
+        };
+
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = ron::from_str(&text).map_err(InputMapError::Parse)?;
+        if version != INPUT_MAP_VERSION {
+            return Err(InputMapError::UnsupportedVersion(version));
+        }
+        ron::from_str(&text).map_err(InputMapError::Parse)
+    }
+
+    /// Writes to a temporary file first and moves it over the old one, so a
+    /// crash halfway through never leaves a broken file behind.
+    pub fn write(&self, path: &Path) -> Result<(), InputMapError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(InputMapError::Serialize)?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(InputMapError::Io)?;
+        }
+        let temporary_path = path.with_extension("ron.tmp");
+        std::fs::write(&temporary_path, text).map_err(InputMapError::Io)?;
+        std::fs::rename(&temporary_path, path).map_err(InputMapError::Io)
+    }
+}
+
+#[derive(Debug)]
+pub enum InputMapError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for InputMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMapError::Io(error) => write!(f, "could not access the input map: {error}"),
+            InputMapError::Parse(error) => write!(f, "could not parse the input map: {error}"),
+            InputMapError::Serialize(error) => {
+                write!(f, "could not write the input map: {error}")
+            }
+            InputMapError::UnsupportedVersion(version) => write!(
+                f,
+                "the input map is version {version}, this game only reads version {INPUT_MAP_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputMapError {}
+
+/// Sent when the player rebinds an action or resets the controls.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct InputMapChanged;
+
+/// How far each action is pushed this frame, from 0 to 1. Keys and buttons
+/// are all or nothing, sticks anywhere in between.
+#[derive(Resource, Debug, Default)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    just_pressed: HashSet<Action>,
+}
+
+impl ActionState {
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.value(action) >= ACTION_PRESS_THRESHOLD
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Direction to move in, at most one unit long. Shorter when a stick is
+    /// only pushed part of the way.
+    pub fn movement(&self) -> Vec2 {
+        Vec2::new(
+            self.value(Action::MoveRight) - self.value(Action::MoveLeft),
+            self.value(Action::MoveUp) - self.value(Action::MoveDown),
+        )
+        .clamp_length_max(1.)
+    }
+}
+
+pub fn update_action_state(
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut action_state: ResMut<ActionState>,
+) {
+    let binding_value = |binding: &Binding| -> f32 {
+        match *binding {
+            Binding::Key(key_code) => f32::from(u8::from(keyboard_input.pressed(key_code))),
+            Binding::GamepadButton(button_type) => {
+                f32::from(u8::from(gamepads.iter().any(|gamepad| {
+                    gamepad_buttons.pressed(GamepadButton::new(gamepad, button_type))
+                })))
+            }
+            Binding::GamepadAxis(axis_type, direction) => gamepads
+                .iter()
+                .map(|gamepad| {
+                    let value = gamepad_axes
+                        .get(GamepadAxis::new(gamepad, axis_type))
+                        .unwrap_or(0.);
+                    let value = match direction {
+                        AxisDirection::Positive => value,
+                        AxisDirection::Negative => -value,
+                    };
+                    ((value - AXIS_DEAD_ZONE) / (1. - AXIS_DEAD_ZONE)).clamp(0., 1.)
+                })
+                .fold(0., f32::max),
+        }
+    };
+
+    let mut just_pressed = HashSet::new();
+    let mut values = HashMap::new();
+    for action in Action::ALL {
+        let value = input_map
+            .bindings(action)
+            .iter()
+            .map(binding_value)
+            .fold(0., f32::max);
+        if value >= ACTION_PRESS_THRESHOLD && !action_state.pressed(action) {
+            just_pressed.insert(action);
+        }
+        values.insert(action, value);
+    }
+    action_state.values = values;
+    action_state.just_pressed = just_pressed;
+}
+
+/// Saves the input map in the user config directory whenever the player
+/// changes it, and loads it back on the next launch. Left out of headless
+/// runs, which take no input.
+pub struct InputMapFilePlugin;
+
+impl Plugin for InputMapFilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputMapFile::default())
+            .add_systems(Startup, (load_input_map,))
+            .add_systems(Update, save_input_map.run_if(on_event::<InputMapChanged>()));
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct InputMapFile {
+    /// `None` when there is nowhere to save to, remapped controls then only
+    /// last until the game is closed.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for InputMapFile {
+    fn default() -> Self {
+        Self {
+            path: dirs::config_dir()
+                .map(|directory| directory.join("bevy_ball_game").join(INPUT_MAP_FILE_NAME)),
+        }
+    }
+}
+
+/// A file that cannot be read is moved aside rather than overwritten, like
+/// the high scores.
+pub fn load_input_map(mut input_map_file: ResMut<InputMapFile>, mut input_map: ResMut<InputMap>) {
+    let path = match input_map_file.path.clone() {
+        Some(path) => path,
+        None => {
+            eprintln!("no user config directory, remapped controls will not be saved");
+            return;
+        }
+    };
+
+    match InputMap::read(&path) {
+        Ok(loaded_input_map) => *input_map = loaded_input_map,
+        Err(error) => {
+            let backup_path = path.with_extension("ron.bak");
+            eprintln!(
+                "{error}, moving {} to {}",
+                path.display(),
+                backup_path.display()
+            );
+            if let Err(error) = std::fs::rename(&path, &backup_path) {
+                eprintln!("could not move the input map aside: {error}, it will not be saved");
+                input_map_file.path = None;
+            }
+        }
+    }
+}
+
+pub fn save_input_map(input_map_file: Res<InputMapFile>, input_map: Res<InputMap>) {
+    if let Some(path) = &input_map_file.path {
+        if let Err(error) = input_map.write(path) {
+            eprintln!("{error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_a_key_keeps_the_other_bindings() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(Action::MoveUp, Binding::Key(KeyCode::I));
+
+        assert_eq!(
+            input_map.bindings(Action::MoveUp),
+            [
+                Binding::Key(KeyCode::I),
+                Binding::Key(KeyCode::Up),
+                Binding::GamepadButton(GamepadButtonType::DPadUp),
+                Binding::GamepadAxis(GamepadAxisType::LeftStickY, AxisDirection::Positive),
+            ]
+        );
+    }
+
+    #[test]
+    fn rebinding_a_button_replaces_the_first_gamepad_binding() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(
+            Action::Ability,
+            Binding::GamepadButton(GamepadButtonType::North),
+        );
+
+        assert_eq!(
+            input_map.bindings(Action::Ability),
+            [
+                Binding::Key(KeyCode::E),
+                Binding::GamepadButton(GamepadButtonType::North),
+            ]
+        );
+    }
+
+    #[test]
+    fn rebinding_to_a_taken_binding_swaps_it() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(Action::MoveUp, Binding::Key(KeyCode::S));
+
+        assert_eq!(
+            input_map.bindings(Action::MoveUp)[0],
+            Binding::Key(KeyCode::S)
+        );
+        assert_eq!(
+            input_map.bindings(Action::MoveDown)[0],
+            Binding::Key(KeyCode::W)
+        );
+    }
+
+    #[test]
+    fn rebinding_to_a_taken_binding_with_nothing_to_swap_unbinds_it() {
+        let mut input_map = InputMap::default();
+        input_map.bindings.insert(Action::Ability, Vec::new());
+        input_map.rebind(Action::Ability, Binding::Key(KeyCode::Space));
+
+        assert_eq!(
+            input_map.bindings(Action::Ability),
+            [Binding::Key(KeyCode::Space)]
+        );
+        assert_eq!(
+            input_map.bindings(Action::Pause),
+            [Binding::GamepadButton(GamepadButtonType::Start)]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_ARENA_WIDTH: f32 = 1280.;
+pub const DEFAULT_ARENA_HEIGHT: f32 = 720.;
+
+/// The playfield every gameplay system works against, with its origin at the
+/// bottom left corner. Its size is logical and does not follow the window; the
+/// camera scales it to fit whatever window it is shown in.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Arena {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_ARENA_WIDTH,
+            height: DEFAULT_ARENA_HEIGHT,
+        }
+    }
+}
+
+impl Arena {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.size() / 2.
+    }
+
+    /// Area the center of an entity of the given size can occupy while the
+    /// entity stays fully inside the arena.
+    pub fn bounds(&self, size: f32) -> Rect {
+        self.inset_bounds(Vec2::splat(size / 2.))
+    }
+
+    /// The arena shrunk by the given amount on each side, or grown when it is
+    /// negative.
+    pub fn inset_bounds(&self, half_extents: Vec2) -> Rect {
+        Rect::from_corners(half_extents, self.size() - half_extents)
+    }
+
+    pub fn random_position(&self, size: f32, rng: &mut impl Rng) -> Vec2 {
+        let bounds = self.bounds(size);
+        let position_x = bounds.min.x + rng.gen::<f32>() * bounds.width();
+        let position_y = bounds.min.y + rng.gen::<f32>() * bounds.height();
+        Vec2::new(position_x, position_y)
+    }
+}
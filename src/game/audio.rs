@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::events::{
+    EnemyBounced, PlaySound, PlayerDied, PowerUpCollected, PowerUpExpired, ShieldBroken,
+    StarCollected,
+};
+
+use super::power_ups::PowerUpKind;
+
+/// How much each volume setting changes per step in the settings menus.
+pub const VOLUME_STEP: f32 = 0.1;
+
+/// Plays sounds for what happens in the game, and any other sounds asked for
+/// with [`PlaySound`]. Left out when running without an audio device.
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySound>()
+            .init_resource::<AudioSettings>()
+            .init_resource::<SoundCooldowns>()
+            .add_systems(Update, (play_gameplay_sounds, play_sounds).chain())
+            .add_systems(
+                Update,
+                apply_audio_settings_to_voices.run_if(resource_changed::<AudioSettings>()),
+            );
+    }
+}
+
+/// Sounds the gameplay asks for, by what they mean rather than by file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundId {
+    StarCollected,
+    PlayerHit,
+    /// An enemy bounced off a wall or another enemy.
+    EnemyBounce,
+    PowerUp(PowerUpKind),
+    PowerUpExpired,
+    ShieldBroken,
+}
+
+/// The mix a sound is played in, each with its own volume setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioChannel {
+    Music,
+    Sfx,
+}
+
+/// How a sound is played.
+#[derive(Debug, Clone, Copy)]
+pub struct SoundDefinition {
+    /// Files to pick from at random, so repeats do not sound the same.
+    pub paths: &'static [&'static str],
+    pub channel: AudioChannel,
+    pub volume: f32,
+    /// Requests within this many seconds of the last time it played are
+    /// dropped.
+    pub cooldown: f32,
+    /// Most copies of the sound playing at once.
+    pub max_voices: usize,
+    /// Playback speed, so one file can make more than one sound.
+    pub pitch: f32,
+    /// Playback speed is picked from `pitch ± pitch_variation`.
+    pub pitch_variation: f32,
+}
+
+impl SoundId {
+    pub fn definition(self) -> SoundDefinition {
+        match self {
+            SoundId::StarCollected => SoundDefinition {
+                paths: &["audio/laserLarge_000.ogg"],
+                channel: AudioChannel::Sfx,
+                volume: 0.8,
+                cooldown: 0.05,
+                max_voices: 3,
+                pitch: 1.,
+                pitch_variation: 0.1,
+            },
+            SoundId::PlayerHit => SoundDefinition {
+                paths: &["audio/explosionCrunch_000.ogg"],
+                channel: AudioChannel::Sfx,
+                volume: 1.,
+                cooldown: 0.,
+                max_voices: 1,
+                pitch: 1.,
+                pitch_variation: 0.,
+            },
+            SoundId::EnemyBounce => SoundDefinition {
+                paths: &["audio/pluck_001.ogg", "audio/pluck_002.ogg"],
+                channel: AudioChannel::Sfx,
+                volume: 0.6,
+                cooldown: 0.08,
+                max_voices: 4,
+                pitch: 1.,
+                pitch_variation: 0.15,
+            },
+            SoundId::PowerUp(kind) => SoundDefinition {
+                paths: match kind {
+                    PowerUpKind::Shield | PowerUpKind::Magnet => &["audio/pluck_001.ogg"],
+                    PowerUpKind::SpeedBoost | PowerUpKind::SlowMo => &["audio/laserLarge_000.ogg"],
+                },
+                channel: AudioChannel::Sfx,
+                volume: 1.,
+                cooldown: 0.,
+                max_voices: 2,
+                pitch: match kind {
+                    PowerUpKind::Shield => 0.6,
+                    PowerUpKind::SpeedBoost => 1.5,
+                    PowerUpKind::SlowMo => 0.5,
+                    PowerUpKind::Magnet => 1.4,
+                },
+                pitch_variation: 0.,
+            },
+            SoundId::PowerUpExpired => SoundDefinition {
+                paths: &["audio/pluck_002.ogg"],
+                channel: AudioChannel::Sfx,
+                volume: 0.8,
+                cooldown: 0.1,
+                max_voices: 2,
+                pitch: 0.5,
+                pitch_variation: 0.,
+            },
+            SoundId::ShieldBroken => SoundDefinition {
+                paths: &["audio/explosionCrunch_000.ogg"],
+                channel: AudioChannel::Sfx,
+                volume: 0.8,
+                cooldown: 0.,
+                max_voices: 1,
+                pitch: 1.8,
+                pitch_variation: 0.,
+            },
+        }
+    }
+}
+
+/// Volumes from 0 to 1, picked by the player.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            music_volume: 0.7,
+            sfx_volume: 1.,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// What a channel's sounds are scaled by, after master volume and mute.
+    pub fn volume(&self, channel: AudioChannel) -> f32 {
+        if self.muted {
+            return 0.;
+        }
+        self.master_volume
+            * match channel {
+                AudioChannel::Music => self.music_volume,
+                AudioChannel::Sfx => self.sfx_volume,
+            }
+    }
+}
+
+/// Raises a volume setting by one step, wrapping from full back to silent.
+pub fn next_volume(volume: f32) -> f32 {
+    let steps = (volume / VOLUME_STEP).round() as i32 + 1;
+    if steps as f32 * VOLUME_STEP > 1. + f32::EPSILON {
+        0.
+    } else {
+        steps as f32 * VOLUME_STEP
+    }
+}
+
+/// When each sound last started playing, in seconds since startup.
+#[derive(Resource, Debug, Default)]
+pub struct SoundCooldowns {
+    pub last_played: HashMap<SoundId, f32>,
+}
+
+/// A sound that is still playing.
+#[derive(Component, Debug)]
+pub struct SoundVoice {
+    pub sound: SoundId,
+    pub channel: AudioChannel,
+    /// Volume of the sound itself, before the settings are applied.
+    pub volume: f32,
+}
+
+pub fn play_gameplay_sounds(
+    mut star_collected_event_reader: EventReader<StarCollected>,
+    mut enemy_bounced_event_reader: EventReader<EnemyBounced>,
+    mut player_died_event_reader: EventReader<PlayerDied>,
+    mut power_up_collected_event_reader: EventReader<PowerUpCollected>,
+    mut power_up_expired_event_reader: EventReader<PowerUpExpired>,
+    mut shield_broken_event_reader: EventReader<ShieldBroken>,
+    mut play_sound_event_writer: EventWriter<PlaySound>,
+) {
+    for _ in star_collected_event_reader.iter() {
+        play_sound_event_writer.send(PlaySound {
+            sound: SoundId::StarCollected,
+        });
+    }
+    for _ in enemy_bounced_event_reader.iter() {
+        play_sound_event_writer.send(PlaySound {
+            sound: SoundId::EnemyBounce,
+        });
+    }
+    for _ in player_died_event_reader.iter() {
+        play_sound_event_writer.send(PlaySound {
+            sound: SoundId::PlayerHit,
+        });
+    }
+    for power_up_collected in power_up_collected_event_reader.iter() {
+        play_sound_event_writer.send(PlaySound {
+            sound: SoundId::PowerUp(power_up_collected.kind),
+        });
+    }
+    for _ in power_up_expired_event_reader.iter() {
+        play_sound_event_writer.send(PlaySound {
+            sound: SoundId::PowerUpExpired,
+        });
+    }
+    for _ in shield_broken_event_reader.iter() {
+        play_sound_event_writer.send(PlaySound {
+            sound: SoundId::ShieldBroken,
+        });
+    }
+}
+
+pub fn play_sounds(
+    mut commands: Commands,
+    mut play_sound_event_reader: EventReader<PlaySound>,
+    asset_server: Res<AssetServer>,
+    audio_settings: Res<AudioSettings>,
+    time: Res<Time>,
+    mut sound_cooldowns: ResMut<SoundCooldowns>,
+    voice_query: Query<&SoundVoice>,
+) {
+    let now = time.elapsed_seconds();
+    // Pitch is picked outside the game RNG, sounds must not change the game.
+    let mut rng = rand::thread_rng();
+    let mut voices: HashMap<SoundId, usize> = HashMap::new();
+    for voice in voice_query.iter() {
+        *voices.entry(voice.sound).or_default() += 1;
+    }
+
+    for event in play_sound_event_reader.iter() {
+        let definition = event.sound.definition();
+        let on_cooldown = sound_cooldowns
+            .last_played
+            .get(&event.sound)
+            .is_some_and(|last_played| now - last_played < definition.cooldown);
+        let playing = voices.entry(event.sound).or_default();
+        if on_cooldown || *playing >= definition.max_voices {
+            continue;
+        }
+        *playing += 1;
+        sound_cooldowns.last_played.insert(event.sound, now);
+
+        let path = definition.paths[rng.gen_range(0..definition.paths.len())];
+        let speed = if definition.pitch_variation > 0. {
+            definition.pitch
+                + rng.gen_range(-definition.pitch_variation..definition.pitch_variation)
+        } else {
+            definition.pitch
+        };
+        commands.spawn((
+            AudioBundle {
+                source: asset_server.load(path),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Despawn,
+                    volume: Volume::new_relative(
+                        definition.volume * audio_settings.volume(definition.channel),
+                    ),
+                    speed,
+                    ..Default::default()
+                },
+            },
+            SoundVoice {
+                sound: event.sound,
+                channel: definition.channel,
+                volume: definition.volume,
+            },
+        ));
+    }
+}
+
+/// Sounds already playing follow the volume settings as they change.
+pub fn apply_audio_settings_to_voices(
+    audio_settings: Res<AudioSettings>,
+    voice_query: Query<(&SoundVoice, &AudioSink)>,
+) {
+    for (voice, sink) in voice_query.iter() {
+        sink.set_volume(voice.volume * audio_settings.volume(voice.channel));
+    }
+}
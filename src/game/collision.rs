@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_ball_game::spatial_hash::SpatialHash;
+
+use crate::AppState;
+
+use super::player::ConfinementSystemSet;
+use super::tick::Position;
+use super::SimulationState;
+
+pub const PLAYER_LAYER: u32 = 1 << 0;
+pub const ENEMY_LAYER: u32 = 1 << 1;
+pub const STAR_LAYER: u32 = 1 << 2;
+pub const POWER_UP_LAYER: u32 = 1 << 3;
+
+/// About the size of an enemy, the biggest collider in the game.
+pub const BROAD_PHASE_CELL_SIZE: f32 = 64.;
+
+/// Detects overlapping colliders every tick, after everything has moved, and
+/// reports when they start and stop touching.
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BroadPhase>()
+            .init_resource::<ActiveCollisions>()
+            .add_event::<CollisionStarted>()
+            .add_event::<CollisionEnded>()
+            .configure_set(FixedUpdate, CollisionSystemSet.after(ConfinementSystemSet))
+            .add_systems(OnExit(AppState::Game), (clear_active_collisions,))
+            .add_systems(
+                FixedUpdate,
+                (
+                    update_broad_phase,
+                    detect_collisions.after(update_broad_phase),
+                )
+                    .in_set(CollisionSystemSet)
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            );
+    }
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct CollisionSystemSet;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    pub layers: CollisionLayers,
+}
+
+impl Collider {
+    pub fn circle(radius: f32, layers: CollisionLayers) -> Self {
+        Self {
+            shape: ColliderShape::Circle { radius },
+            layers,
+        }
+    }
+
+    pub fn aabb(half_extents: Vec2, layers: CollisionLayers) -> Self {
+        Self {
+            shape: ColliderShape::Aabb { half_extents },
+            layers,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderShape {
+    Circle { radius: f32 },
+    Aabb { half_extents: Vec2 },
+}
+
+impl ColliderShape {
+    /// Half the size of the box that contains the shape.
+    pub fn half_extents(&self) -> Vec2 {
+        match *self {
+            ColliderShape::Circle { radius } => Vec2::splat(radius),
+            ColliderShape::Aabb { half_extents } => half_extents,
+        }
+    }
+
+    pub fn intersects(&self, position: Vec2, other: &ColliderShape, other_position: Vec2) -> bool {
+        match (*self, *other) {
+            (
+                ColliderShape::Circle { radius },
+                ColliderShape::Circle {
+                    radius: other_radius,
+                },
+            ) => position.distance(other_position) <= radius + other_radius,
+            (
+                ColliderShape::Aabb { half_extents },
+                ColliderShape::Aabb {
+                    half_extents: other_half_extents,
+                },
+            ) => {
+                let distance = (position - other_position).abs();
+                distance.x <= half_extents.x + other_half_extents.x
+                    && distance.y <= half_extents.y + other_half_extents.y
+            }
+            (ColliderShape::Circle { radius }, ColliderShape::Aabb { half_extents }) => {
+                circle_intersects_aabb(position, radius, other_position, half_extents)
+            }
+            (ColliderShape::Aabb { half_extents }, ColliderShape::Circle { radius }) => {
+                circle_intersects_aabb(other_position, radius, position, half_extents)
+            }
+        }
+    }
+}
+
+fn circle_intersects_aabb(center: Vec2, radius: f32, box_center: Vec2, half_extents: Vec2) -> bool {
+    let closest_point = center.clamp(box_center - half_extents, box_center + half_extents);
+    center.distance(closest_point) <= radius
+}
+
+/// Which layers a collider is on and which layers it collides with. Two
+/// colliders only collide when each one's mask contains a layer of the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionLayers {
+    pub layers: u32,
+    pub mask: u32,
+}
+
+impl CollisionLayers {
+    pub fn new(layers: u32, mask: u32) -> Self {
+        Self { layers, mask }
+    }
+
+    pub fn interacts_with(&self, other: &CollisionLayers) -> bool {
+        self.mask & other.layers != 0 && other.mask & self.layers != 0
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CollisionStarted {
+    pub entities: (Entity, Entity),
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CollisionEnded {
+    pub entities: (Entity, Entity),
+}
+
+impl CollisionStarted {
+    /// Returns the pair ordered as `(first, second)` when one entity passes
+    /// `is_first` and the other passes `is_second`.
+    pub fn between(
+        &self,
+        is_first: impl Fn(Entity) -> bool,
+        is_second: impl Fn(Entity) -> bool,
+    ) -> Option<(Entity, Entity)> {
+        order_pair(self.entities, is_first, is_second)
+    }
+}
+
+impl CollisionEnded {
+    /// Returns the pair ordered as `(first, second)` when one entity passes
+    /// `is_first` and the other passes `is_second`.
+    pub fn between(
+        &self,
+        is_first: impl Fn(Entity) -> bool,
+        is_second: impl Fn(Entity) -> bool,
+    ) -> Option<(Entity, Entity)> {
+        order_pair(self.entities, is_first, is_second)
+    }
+}
+
+fn order_pair(
+    (a, b): (Entity, Entity),
+    is_first: impl Fn(Entity) -> bool,
+    is_second: impl Fn(Entity) -> bool,
+) -> Option<(Entity, Entity)> {
+    if is_first(a) && is_second(b) {
+        Some((a, b))
+    } else if is_first(b) && is_second(a) {
+        Some((b, a))
+    } else {
+        None
+    }
+}
+
+/// Every collider bucketed by position, rebuilt each tick. Only colliders
+/// sharing a cell are tested against each other.
+#[derive(Resource)]
+pub struct BroadPhase {
+    pub grid: SpatialHash<Entity>,
+}
+
+impl Default for BroadPhase {
+    fn default() -> Self {
+        Self {
+            grid: SpatialHash::new(BROAD_PHASE_CELL_SIZE),
+        }
+    }
+}
+
+/// Pairs of entities that were touching at the end of the last tick.
+#[derive(Resource, Default)]
+pub struct ActiveCollisions {
+    pub pairs: HashSet<(Entity, Entity)>,
+}
+
+impl ActiveCollisions {
+    /// Returns the touching pairs where one entity passes `is_first` and the
+    /// other passes `is_second`, each ordered as `(first, second)`. Hash set
+    /// order is not stable, so the pairs come in no particular order.
+    pub fn between<'a>(
+        &'a self,
+        is_first: impl Fn(Entity) -> bool + 'a,
+        is_second: impl Fn(Entity) -> bool + 'a,
+    ) -> impl Iterator<Item = (Entity, Entity)> + 'a {
+        self.pairs
+            .iter()
+            .filter_map(move |&pair| order_pair(pair, &is_first, &is_second))
+    }
+}
+
+pub fn update_broad_phase(
+    collider_query: Query<(Entity, &Position, &Collider)>,
+    mut broad_phase: ResMut<BroadPhase>,
+) {
+    broad_phase.grid.clear();
+    for (entity, position, collider) in collider_query.iter() {
+        broad_phase
+            .grid
+            .insert(entity, position.0, collider.shape.half_extents());
+    }
+}
+
+pub fn detect_collisions(
+    collider_query: Query<(&Position, &Collider)>,
+    broad_phase: Res<BroadPhase>,
+    mut active_collisions: ResMut<ActiveCollisions>,
+    mut collision_started_event_writer: EventWriter<CollisionStarted>,
+    mut collision_ended_event_writer: EventWriter<CollisionEnded>,
+) {
+    let mut pairs = HashSet::new();
+
+    for (entity, other_entity) in broad_phase.grid.candidate_pairs() {
+        if let Ok([(position, collider), (other_position, other_collider)]) =
+            collider_query.get_many([entity, other_entity])
+        {
+            if collider.layers.interacts_with(&other_collider.layers)
+                && collider
+                    .shape
+                    .intersects(position.0, &other_collider.shape, other_position.0)
+            {
+                pairs.insert((entity, other_entity));
+            }
+        }
+    }
+
+    send_collision_changes(
+        pairs,
+        &mut active_collisions,
+        &mut collision_started_event_writer,
+        &mut collision_ended_event_writer,
+    );
+}
+
+/// Compares the touching pairs of this tick with the ones of the last tick and
+/// sends the events for the differences.
+fn send_collision_changes(
+    pairs: HashSet<(Entity, Entity)>,
+    active_collisions: &mut ActiveCollisions,
+    collision_started_event_writer: &mut EventWriter<CollisionStarted>,
+    collision_ended_event_writer: &mut EventWriter<CollisionEnded>,
+) {
+    let mut started: Vec<_> = pairs
+        .difference(&active_collisions.pairs)
+        .copied()
+        .collect();
+    let mut ended: Vec<_> = active_collisions
+        .pairs
+        .difference(&pairs)
+        .copied()
+        .collect();
+    // Hash set order is not stable, sort to keep the runs reproducible.
+    started.sort();
+    ended.sort();
+
+    for entities in ended {
+        collision_ended_event_writer.send(CollisionEnded { entities });
+    }
+    for entities in started {
+        collision_started_event_writer.send(CollisionStarted { entities });
+    }
+
+    active_collisions.pairs = pairs;
+}
+
+pub fn clear_active_collisions(
+    mut broad_phase: ResMut<BroadPhase>,
+    mut active_collisions: ResMut<ActiveCollisions>,
+) {
+    broad_phase.grid.clear();
+    active_collisions.pairs.clear();
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+
+use super::arena::Arena;
+use super::collision::Collider;
+use super::player::ConfinementSystemSet;
+use super::tick::{Position, PreviousPosition, Velocity};
+use super::SimulationState;
+
+/// Keeps every entity with a [`ConfinedToArena`] component inside the arena,
+/// after everything has moved.
+pub struct ConfinementPlugin;
+
+impl Plugin for ConfinementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BouncedOffArena>().add_systems(
+            FixedUpdate,
+            confine_to_arena
+                .in_set(ConfinementSystemSet)
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(SimulationState::Running)),
+        );
+    }
+}
+
+/// What happens when an entity reaches the edge of the arena. The size of the
+/// entity is taken from its collider, if it has one.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfinedToArena {
+    /// Stops at the edge, fully inside the arena.
+    Clamp,
+    /// Stops at the edge and its velocity is reflected away from it.
+    Bounce,
+    /// Leaves the arena and comes back in on the opposite side.
+    Wrap,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BouncedOffArena {
+    pub entity: Entity,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn confine_to_arena(
+    arena: Res<Arena>,
+    mut confined_query: Query<(
+        Entity,
+        &ConfinedToArena,
+        &mut Position,
+        Option<&mut PreviousPosition>,
+        Option<&mut Velocity>,
+        Option<&Collider>,
+    )>,
+    mut bounced_off_arena_event_writer: EventWriter<BouncedOffArena>,
+) {
+    for (entity, confined, mut position, previous_position, velocity, collider) in
+        confined_query.iter_mut()
+    {
+        let half_extents = collider.map_or(Vec2::ZERO, |collider| collider.shape.half_extents());
+
+        match confined {
+            ConfinedToArena::Clamp => {
+                let bounds = arena.inset_bounds(half_extents);
+                position.0 = position.clamp(bounds.min, bounds.max);
+            }
+            ConfinedToArena::Bounce => {
+                let bounds = arena.inset_bounds(half_extents);
+                let clamped_position = position.clamp(bounds.min, bounds.max);
+                if clamped_position != position.0 {
+                    if let Some(mut velocity) = velocity {
+                        // Only flip towards the inside, so an entity that is
+                        // already heading back in keeps going.
+                        if position.x < bounds.min.x {
+                            velocity.x = velocity.x.abs();
+                        } else if position.x > bounds.max.x {
+                            velocity.x = -velocity.x.abs();
+                        }
+                        if position.y < bounds.min.y {
+                            velocity.y = velocity.y.abs();
+                        } else if position.y > bounds.max.y {
+                            velocity.y = -velocity.y.abs();
+                        }
+                    }
+                    position.0 = clamped_position;
+                    bounced_off_arena_event_writer.send(BouncedOffArena { entity });
+                }
+            }
+            ConfinedToArena::Wrap => {
+                // Wrap once the entity has fully left, so it never shows on
+                // both sides at the same time.
+                let bounds = arena.inset_bounds(-half_extents);
+                let mut offset = Vec2::ZERO;
+                if position.x < bounds.min.x {
+                    offset.x = bounds.width();
+                } else if position.x > bounds.max.x {
+                    offset.x = -bounds.width();
+                }
+                if position.y < bounds.min.y {
+                    offset.y = bounds.height();
+                } else if position.y > bounds.max.y {
+                    offset.y = -bounds.height();
+                }
+                position.0 += offset;
+                // Move the previous position along so the rendering does not
+                // slide across the whole arena.
+                if let Some(mut previous_position) = previous_position {
+                    previous_position.0 += offset;
+                }
+            }
+        }
+    }
+}
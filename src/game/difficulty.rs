@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+use super::enemy::spawn_enemies;
+use super::mode::Difficulty;
+use super::player::MovementSystemSet;
+use super::score::Score;
+use super::tick::{count_ticks, tick_seconds, TickCount};
+use super::tuning::Tuning;
+use super::SimulationState;
+
+/// Makes each game harder the longer it lasts and the more stars the player
+/// collects, following the curves in the tuning, at the pace of the difficulty
+/// picked.
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DifficultyDirector>()
+            .configure_set(FixedUpdate, DifficultySystemSet.before(MovementSystemSet))
+            .add_systems(
+                OnEnter(AppState::Game),
+                (reset_difficulty_director.before(spawn_enemies),),
+            )
+            .add_systems(
+                FixedUpdate,
+                direct_difficulty
+                    .in_set(DifficultySystemSet)
+                    .after(count_ticks)
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            );
+    }
+}
+
+/// Updates the [`DifficultyDirector`] at the start of every tick.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DifficultySystemSet;
+
+/// A multiplier that changes with how far a game has got. Linear between its
+/// points and flat past the first and last.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Curve {
+    /// `(progress, multiplier)` pairs, in order of progress.
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Curve {
+    pub fn new(points: &[(f32, f32)]) -> Self {
+        Self {
+            points: points.to_vec(),
+        }
+    }
+
+    pub fn sample(&self, progress: f32) -> f32 {
+        let Some(&(first_progress, first_multiplier)) = self.points.first() else {
+            return 1.;
+        };
+        if progress <= first_progress {
+            return first_multiplier;
+        }
+
+        for pair in self.points.windows(2) {
+            let ((from_progress, from), (to_progress, to)) = (pair[0], pair[1]);
+            if progress <= to_progress {
+                let t = (progress - from_progress) / (to_progress - from_progress);
+                return from + (to - from) * t;
+            }
+        }
+        self.points[self.points.len() - 1].1
+    }
+
+    fn validate(&self, name: &str, problems: &mut Vec<String>) {
+        for &(progress, multiplier) in &self.points {
+            if !progress.is_finite() || !multiplier.is_finite() || multiplier <= 0. {
+                problems.push(format!(
+                    "`{name}` has the point ({progress}, {multiplier}), multipliers must be more than zero"
+                ));
+            }
+        }
+        if self.points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            problems.push(format!("`{name}` points must be in order of progress"));
+        }
+    }
+}
+
+/// How one value changes over a game: its multipliers by the seconds played
+/// and by the stars collected are applied together. Stars rather than points,
+/// so the harder difficulties' bigger scores do not speed the curve up on top
+/// of their pace.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Ramp {
+    pub by_time: Curve,
+    pub by_score: Curve,
+}
+
+impl Ramp {
+    pub fn sample(&self, seconds: f32, stars: f32) -> f32 {
+        self.by_time.sample(seconds) * self.by_score.sample(stars)
+    }
+}
+
+/// Curves scaling the base tuning values as a game goes on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DifficultyCurves {
+    pub enemy_spawn_duration: Ramp,
+    pub enemy_speed: Ramp,
+    /// Stars get scarcer as the time between them grows.
+    pub star_spawn_duration: Ramp,
+}
+
+impl Default for DifficultyCurves {
+    fn default() -> Self {
+        Self {
+            enemy_spawn_duration: Ramp {
+                by_time: Curve::new(&[(0., 1.), (120., 0.4)]),
+                by_score: Curve::new(&[(0., 1.), (50., 0.7)]),
+            },
+            enemy_speed: Ramp {
+                by_time: Curve::new(&[(0., 1.), (120., 1.6)]),
+                by_score: Curve::new(&[(0., 1.), (50., 1.2)]),
+            },
+            star_spawn_duration: Ramp {
+                by_time: Curve::new(&[(0., 1.), (120., 2.)]),
+                by_score: Curve::new(&[(0., 1.), (50., 1.5)]),
+            },
+        }
+    }
+}
+
+impl DifficultyCurves {
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        for (name, ramp) in [
+            ("enemy_spawn_duration", &self.enemy_spawn_duration),
+            ("enemy_speed", &self.enemy_speed),
+            ("star_spawn_duration", &self.star_spawn_duration),
+        ] {
+            ramp.by_time
+                .validate(&format!("difficulty_curves.{name}.by_time"), problems);
+            ramp.by_score
+                .validate(&format!("difficulty_curves.{name}.by_score"), problems);
+        }
+    }
+}
+
+/// How a difficulty plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyPreset {
+    /// How fast the game moves along the curves, 1 follows them as written.
+    pub pace: f32,
+    /// Points each star is worth.
+    pub score_multiplier: u32,
+}
+
+impl Difficulty {
+    pub fn preset(self) -> DifficultyPreset {
+        match self {
+            Difficulty::Easy => DifficultyPreset {
+                pace: 0.6,
+                score_multiplier: 1,
+            },
+            Difficulty::Normal => DifficultyPreset {
+                pace: 1.,
+                score_multiplier: 1,
+            },
+            Difficulty::Hard => DifficultyPreset {
+                pace: 1.5,
+                score_multiplier: 2,
+            },
+            Difficulty::Insane => DifficultyPreset {
+                pace: 2.5,
+                score_multiplier: 3,
+            },
+        }
+    }
+}
+
+/// The values the current game is played with right now.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyDirector {
+    pub enemy_spawn_duration: f32,
+    pub enemy_speed: f32,
+    pub star_spawn_duration: f32,
+    pub score_multiplier: u32,
+}
+
+impl Default for DifficultyDirector {
+    fn default() -> Self {
+        Self::new(&Tuning::default(), Difficulty::default(), 0., 0)
+    }
+}
+
+impl DifficultyDirector {
+    pub fn new(tuning: &Tuning, difficulty: Difficulty, seconds: f32, stars: u32) -> Self {
+        let preset = difficulty.preset();
+        let curves = &tuning.difficulty_curves;
+        let (seconds, stars) = (seconds * preset.pace, stars as f32 * preset.pace);
+
+        Self {
+            enemy_spawn_duration: tuning.enemy_spawn_duration
+                * curves.enemy_spawn_duration.sample(seconds, stars),
+            enemy_speed: tuning.enemy_speed * curves.enemy_speed.sample(seconds, stars),
+            star_spawn_duration: tuning.star_spawn_duration
+                * curves.star_spawn_duration.sample(seconds, stars),
+            score_multiplier: preset.score_multiplier,
+        }
+    }
+}
+
+pub fn reset_difficulty_director(
+    mut difficulty_director: ResMut<DifficultyDirector>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+) {
+    *difficulty_director = DifficultyDirector::new(&tuning, *difficulty, 0., 0);
+}
+
+pub fn direct_difficulty(
+    mut difficulty_director: ResMut<DifficultyDirector>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    tick_count: Res<TickCount>,
+    fixed_time: Res<FixedTime>,
+    score: Res<Score>,
+) {
+    let seconds = tick_count.ticks as f32 * tick_seconds(&fixed_time);
+    let directed = DifficultyDirector::new(&tuning, *difficulty, seconds, score.stars);
+    // Only a real change should trigger the systems that follow it.
+    if *difficulty_director != directed {
+        *difficulty_director = directed;
+    }
+}
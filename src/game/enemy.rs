@@ -1,208 +1,395 @@
-use bevy::audio::PlaybackMode;
+use std::time::Duration;
+
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
-use rand::random;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
+use crate::events::{EnemyBounced, EnemySpawned};
 use crate::AppState;
 
+use super::arena::Arena;
+use super::collision::{
+    ActiveCollisions, Collider, CollisionLayers, CollisionSystemSet, ENEMY_LAYER, PLAYER_LAYER,
+};
+use super::confinement::{BouncedOffArena, ConfinedToArena};
+use super::difficulty::{reset_difficulty_director, DifficultyDirector, DifficultySystemSet};
+use super::enemy_kinds::{insert_behavior, EnemyKindDefinition};
+use super::mode::GameMode;
+use super::player::{ConfinementSystemSet, MovementSystemSet};
+use super::power_ups::{reset_active_power_ups, ActivePowerUps};
+use super::rng::{reset_game_rng, GameRng};
+use super::steering::Steering;
+use super::tick::{Position, PositionBundle, TimeScale, Velocity};
+use super::tuning::Tuning;
 use super::SimulationState;
 
-pub const NUMBER_OF_ENEMIES: usize = 4;
-pub const ENEMY_SIZE: f32 = 64.;
-pub const ENEMY_SPEED: f32 = 200.;
-pub const ENEMY_SPAWN_DURATION: f32 = 5.;
-
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<EnemySpawnTimer>()
-            .add_systems(OnEnter(AppState::Game), (spawn_enemies,))
+            .init_resource::<EnemySpeed>()
+            .add_systems(
+                OnEnter(AppState::Game),
+                (
+                    reset_enemy_spawn_timer,
+                    reset_enemy_speed.after(reset_difficulty_director),
+                    spawn_enemies
+                        .after(reset_game_rng)
+                        .after(reset_active_power_ups)
+                        .run_if(resource_equals(GameMode::Endless)),
+                ),
+            )
             .add_systems(OnExit(AppState::Game), (despawn_enemies,))
             .add_systems(
-                Update,
+                FixedUpdate,
                 (
-                    enemy_movement,
-                    update_enemy_direction,
-                    confine_enemy_movement,
-                    tick_enemy_spawn_timer,
-                    spawn_enemies_over_time,
+                    (
+                        apply_difficulty_to_enemy_spawn_timer,
+                        apply_difficulty_to_enemy_speeds.before(MovementSystemSet),
+                    )
+                        .after(DifficultySystemSet),
+                    report_enemy_wall_bounces.after(ConfinementSystemSet),
+                    bounce_enemies_off_each_other.after(CollisionSystemSet),
+                    tick_enemy_spawn_timer.after(apply_difficulty_to_enemy_spawn_timer),
+                    spawn_enemies_over_time
+                        .after(tick_enemy_spawn_timer)
+                        .run_if(resource_equals(GameMode::Endless)),
                 )
                     .run_if(in_state(AppState::Game))
                     .run_if(in_state(SimulationState::Running)),
+            )
+            .add_systems(
+                Update,
+                apply_tuning_to_enemy_colliders.run_if(resource_changed::<Tuning>()),
             );
     }
 }
 
 #[derive(Component)]
-pub struct Enemy {
-    pub direction: Vec2,
+pub struct Enemy {}
+
+/// What an enemy looks like and how it moves, set for each kind in the
+/// tuning. Waves say which kind each of their enemies is.
+#[derive(
+    Component,
+    Serialize,
+    Deserialize,
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum EnemyKind {
+    /// Heads off in a random direction and bounces off everything.
+    #[default]
+    Bouncer,
+    /// Homes in on the player.
+    Chaser,
+    /// Swerves from side to side of its heading.
+    Zigzagger,
+    /// Circles a point.
+    Orbiter,
+    /// Stops to flash, then lunges at the player.
+    Dasher,
+    /// Breaks into smaller enemies when it bounces.
+    Splitter,
 }
 
+/// Size of an enemy relative to others of its kind, smaller for the pieces
+/// of a split enemy.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct EnemyScale(pub f32);
+
 #[derive(Resource)]
 pub struct EnemySpawnTimer {
     pub timer: Timer,
 }
 
+/// The enemy speed of the [`DifficultyDirector`] the enemies in the arena
+/// were last set to move at.
+#[derive(Resource, Default)]
+pub struct EnemySpeed(pub f32);
+
 impl Default for EnemySpawnTimer {
     fn default() -> Self {
         Self {
-            timer: Timer::from_seconds(ENEMY_SPAWN_DURATION, TimerMode::Repeating),
+            timer: Timer::from_seconds(
+                Tuning::default().enemy_spawn_duration,
+                TimerMode::Repeating,
+            ),
         }
     }
 }
 
+/// Size the sprite of an enemy is drawn at.
+pub fn enemy_size(tuning: &Tuning, definition: &EnemyKindDefinition, scale: EnemyScale) -> f32 {
+    tuning.enemy_size * definition.size * scale.0
+}
+
+pub fn enemy_collider(
+    tuning: &Tuning,
+    definition: &EnemyKindDefinition,
+    scale: EnemyScale,
+) -> Collider {
+    let mask = if tuning.enemies_collide {
+        PLAYER_LAYER | ENEMY_LAYER
+    } else {
+        PLAYER_LAYER
+    };
+    Collider::circle(
+        enemy_size(tuning, definition, scale) * definition.collider,
+        CollisionLayers::new(ENEMY_LAYER, mask),
+    )
+}
+
+/// Spawns an enemy heading in a random direction, at `speed` times the
+/// speed of its kind.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    kind: EnemyKind,
+    position: Vec2,
+    speed: f32,
+    scale: f32,
+    tuning: &Tuning,
+    active_power_ups: &ActivePowerUps,
+    game_rng: &mut GameRng,
+) -> Entity {
+    let definition = tuning.enemy_kind(kind);
+    let scale = EnemyScale(scale);
+    let mut velocity = Vec2::new(
+        (game_rng.gen::<f32>() * 2.) - 1.,
+        (game_rng.gen::<f32>() * 2.) - 1.,
+    )
+    .normalize()
+        * speed
+        * definition.speed;
+
+    let mut enemy = commands.spawn((
+        PositionBundle::new(position),
+        enemy_collider(tuning, definition, scale),
+        ConfinedToArena::Bounce,
+        Enemy {},
+        kind,
+        scale,
+        // Set here rather than left to slow-mo, or a new enemy would get a
+        // tick at full speed.
+        TimeScale(active_power_ups.enemy_time_scale(&tuning.power_ups)),
+    ));
+    insert_behavior(&mut enemy, &definition.behavior, &mut velocity, game_rng);
+    enemy.insert(Velocity(velocity)).id()
+}
+
 pub fn spawn_enemies(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    difficulty_director: Res<DifficultyDirector>,
+    active_power_ups: Res<ActivePowerUps>,
+    mut game_rng: ResMut<GameRng>,
+    mut enemy_spawned_event_writer: EventWriter<EnemySpawned>,
 ) {
-    let window = window_query.get_single().unwrap();
-
-    let half_enemy_size = ENEMY_SIZE / 2.;
-    let x_min = 0. + half_enemy_size;
-    let x_max = window.width() - half_enemy_size;
-    let y_min = 0. + half_enemy_size;
-    let y_max = window.height() - half_enemy_size;
-
-    for _ in 0..NUMBER_OF_ENEMIES {
-        let position_x = x_min + random::<f32>() * (x_max - x_min);
-        let position_y = y_min + random::<f32>() * (y_max - y_min);
-
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_xyz(position_x, position_y, 0.),
-                texture: asset_server.load("sprites/ball_red_large.png"),
-                ..Default::default()
-            },
-            Enemy {
-                direction: Vec2::new((random::<f32>() * 2.) - 1., (random::<f32>() * 2.) - 1.)
-                    .normalize(),
-            },
-        ));
+    for _ in 0..tuning.number_of_enemies {
+        let position = arena.random_position(tuning.enemy_size, &mut *game_rng);
+        let kind = tuning.endless_kinds[game_rng.gen_range(0..tuning.endless_kinds.len())];
+        let entity = spawn_enemy(
+            &mut commands,
+            kind,
+            position,
+            difficulty_director.enemy_speed,
+            1.,
+            &tuning,
+            &active_power_ups,
+            &mut game_rng,
+        );
+        enemy_spawned_event_writer.send(EnemySpawned { entity, position });
     }
 }
 
+/// A new game starts a full spawn interval away from its first new enemy,
+/// however far the last game got.
+pub fn reset_enemy_spawn_timer(mut enemy_spawn_timer: ResMut<EnemySpawnTimer>) {
+    enemy_spawn_timer.timer.reset();
+}
+
+pub fn reset_enemy_speed(
+    mut enemy_speed: ResMut<EnemySpeed>,
+    difficulty_director: Res<DifficultyDirector>,
+) {
+    enemy_speed.0 = difficulty_director.enemy_speed;
+}
+
 pub fn despawn_enemies(mut commands: Commands, enemy_query: Query<Entity, With<Enemy>>) {
     enemy_query.for_each(|enemy| {
         commands.entity(enemy).despawn();
     })
 }
 
-pub fn enemy_movement(mut enemy_query: Query<(&mut Transform, &Enemy)>, time: Res<Time>) {
-    for (mut transform, enemy) in enemy_query.iter_mut() {
-        let direction = Vec3::new(enemy.direction.x, enemy.direction.y, 0.);
-        transform.translation += direction * ENEMY_SPEED * time.delta_seconds();
-    }
-}
-
-pub fn update_enemy_direction(
-    mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut enemy_query: Query<(&Transform, &mut Enemy)>,
-    asset_server: Res<AssetServer>,
+pub fn report_enemy_wall_bounces(
+    mut bounced_off_arena_event_reader: EventReader<BouncedOffArena>,
+    enemy_query: Query<&Position, With<Enemy>>,
+    mut enemy_bounced_event_writer: EventWriter<EnemyBounced>,
 ) {
-    let window = window_query.get_single().unwrap();
-
-    let half_enemy_size = ENEMY_SIZE / 2.;
-    let x_min = 0. + half_enemy_size;
-    let x_max = window.width() - half_enemy_size;
-    let y_min = 0. + half_enemy_size;
-    let y_max = window.height() - half_enemy_size;
-
-    let mut direction_changed = false;
-
-    for (transform, mut enemy) in enemy_query.iter_mut() {
-        let translation = transform.translation;
-        if translation.x < x_min {
-            enemy.direction.x = enemy.direction.x.abs();
-            direction_changed = true;
-        } else if translation.x > x_max {
-            enemy.direction.x = -enemy.direction.x.abs();
-            direction_changed = true;
-        }
-        if translation.y < y_min {
-            enemy.direction.y = enemy.direction.y.abs();
-            direction_changed = true;
-        } else if translation.y > y_max {
-            enemy.direction.y = -enemy.direction.y.abs();
-            direction_changed = true;
+    for bounce in bounced_off_arena_event_reader.iter() {
+        if let Ok(position) = enemy_query.get(bounce.entity) {
+            enemy_bounced_event_writer.send(EnemyBounced {
+                entity: bounce.entity,
+                position: position.0,
+                other: None,
+            });
         }
     }
-    if direction_changed {
-        commands.spawn(AudioBundle {
-            source: asset_server.load("audio/pluck_002.ogg"),
-            settings: PlaybackSettings {
-                mode: PlaybackMode::Despawn,
-                ..Default::default()
-            },
-        });
-    }
 }
 
-pub fn confine_enemy_movement(
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut enemy_query: Query<&mut Transform, With<Enemy>>,
+/// Elastic collision between two enemies of equal mass: they swap the parts of
+/// their velocities along the line between their centers. Each enemy then gets
+/// its own speed back, so a bounce never speeds an enemy up or slows it down.
+pub fn bounce_enemies_off_each_other(
+    tuning: Res<Tuning>,
+    active_collisions: Res<ActiveCollisions>,
+    mut enemy_query: Query<(&Position, &mut Velocity), With<Enemy>>,
+    mut enemy_bounced_event_writer: EventWriter<EnemyBounced>,
 ) {
-    if let Ok(mut transform) = enemy_query.get_single_mut() {
-        let window = window_query.get_single().unwrap();
-
-        let half_enemy_size = ENEMY_SIZE / 2.;
+    if !tuning.enemies_collide {
+        return;
+    }
 
-        let x_min = 0. + half_enemy_size;
-        let x_max = window.width() - half_enemy_size;
-        let y_min = 0. + half_enemy_size;
-        let y_max = window.height() - half_enemy_size;
+    let mut pairs: Vec<_> = active_collisions
+        .pairs
+        .iter()
+        .copied()
+        .filter(|&(a, b)| enemy_query.contains(a) && enemy_query.contains(b))
+        .collect();
+    // Hash set order is not stable, sort to keep the runs reproducible.
+    pairs.sort();
 
-        let mut translation = transform.translation;
+    for (a, b) in pairs {
+        if let Ok([(position, mut velocity), (other_position, mut other_velocity)]) =
+            enemy_query.get_many_mut([a, b])
+        {
+            let normal = (other_position.0 - position.0).normalize_or_zero();
+            let approach_speed = (velocity.0 - other_velocity.0).dot(normal);
+            // Already moving apart, after an earlier bounce or a wall.
+            if approach_speed <= 0. {
+                continue;
+            }
 
-        if translation.x < x_min {
-            translation.x = x_min;
-        } else if translation.x > x_max {
-            translation.x = x_max;
+            let (speed, other_speed) = (velocity.length(), other_velocity.length());
+            let direction = (velocity.0 - normal * approach_speed).normalize_or_zero();
+            let other_direction = (other_velocity.0 + normal * approach_speed).normalize_or_zero();
+            // An enemy hitting one that moves straight across its path would
+            // stop dead, send it back the way it came instead.
+            velocity.0 = if direction == Vec2::ZERO {
+                -normal
+            } else {
+                direction
+            } * speed;
+            other_velocity.0 = if other_direction == Vec2::ZERO {
+                normal
+            } else {
+                other_direction
+            } * other_speed;
+            enemy_bounced_event_writer.send_batch([
+                EnemyBounced {
+                    entity: a,
+                    position: position.0,
+                    other: Some(b),
+                },
+                EnemyBounced {
+                    entity: b,
+                    position: other_position.0,
+                    other: Some(a),
+                },
+            ]);
         }
-        if translation.y < y_min {
-            translation.y = y_min;
-        } else if translation.y > y_max {
-            translation.y = y_max;
-        }
-
-        transform.translation = translation;
     }
 }
 
-pub fn tick_enemy_spawn_timer(mut enemy_spawn_timer: ResMut<EnemySpawnTimer>, time: Res<Time>) {
-    enemy_spawn_timer.timer.tick(time.delta());
+pub fn tick_enemy_spawn_timer(
+    mut enemy_spawn_timer: ResMut<EnemySpawnTimer>,
+    fixed_time: Res<FixedTime>,
+) {
+    enemy_spawn_timer.timer.tick(fixed_time.period);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_enemies_over_time(
     mut commands: Commands,
     enemy_spawn_timer: Res<EnemySpawnTimer>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    difficulty_director: Res<DifficultyDirector>,
+    active_power_ups: Res<ActivePowerUps>,
+    mut game_rng: ResMut<GameRng>,
+    mut enemy_spawned_event_writer: EventWriter<EnemySpawned>,
 ) {
     if enemy_spawn_timer.timer.finished() {
-        let window = window_query.get_single().unwrap();
-
-        let half_enemy_size = ENEMY_SIZE / 2.;
-        let x_min = 0. + half_enemy_size;
-        let x_max = window.width() - half_enemy_size;
-        let y_min = 0. + half_enemy_size;
-        let y_max = window.height() - half_enemy_size;
-
-        let position_x = x_min + random::<f32>() * (x_max - x_min);
-        let position_y = y_min + random::<f32>() * (y_max - y_min);
-
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_xyz(position_x, position_y, 0.),
-                texture: asset_server.load("sprites/ball_red_large.png"),
-                ..Default::default()
-            },
-            Enemy {
-                direction: Vec2::new((random::<f32>() * 2.) - 1., (random::<f32>() * 2.) - 1.)
-                    .normalize(),
-            },
-        ));
+        let position = arena.random_position(tuning.enemy_size, &mut *game_rng);
+        let kind = tuning.endless_kinds[game_rng.gen_range(0..tuning.endless_kinds.len())];
+        let entity = spawn_enemy(
+            &mut commands,
+            kind,
+            position,
+            difficulty_director.enemy_speed,
+            1.,
+            &tuning,
+            &active_power_ups,
+            &mut game_rng,
+        );
+        enemy_spawned_event_writer.send(EnemySpawned { entity, position });
+    }
+}
+
+pub fn apply_difficulty_to_enemy_spawn_timer(
+    mut enemy_spawn_timer: ResMut<EnemySpawnTimer>,
+    difficulty_director: Res<DifficultyDirector>,
+) {
+    if difficulty_director.is_changed() {
+        enemy_spawn_timer
+            .timer
+            .set_duration(Duration::from_secs_f32(
+                difficulty_director.enemy_spawn_duration,
+            ));
+    }
+}
+
+/// Steered enemies get a new top speed and steer to it themselves. The others
+/// are sped up or slowed down by as much as the enemy speed changed, so one
+/// going slower than its kind, stopped or lunging stays that way.
+pub fn apply_difficulty_to_enemy_speeds(
+    difficulty_director: Res<DifficultyDirector>,
+    tuning: Res<Tuning>,
+    mut enemy_speed: ResMut<EnemySpeed>,
+    mut enemy_query: Query<(&EnemyKind, &mut Velocity, Option<&mut Steering>), With<Enemy>>,
+) {
+    if !difficulty_director.is_changed() || difficulty_director.enemy_speed == enemy_speed.0 {
+        return;
+    }
+
+    let change = difficulty_director.enemy_speed / enemy_speed.0;
+    for (kind, mut velocity, steering) in enemy_query.iter_mut() {
+        match steering {
+            Some(mut steering) => {
+                steering.max_speed =
+                    difficulty_director.enemy_speed * tuning.enemy_kind(*kind).speed;
+            }
+            None => velocity.0 *= change,
+        }
+    }
+    enemy_speed.0 = difficulty_director.enemy_speed;
+}
+
+pub fn apply_tuning_to_enemy_colliders(
+    tuning: Res<Tuning>,
+    mut enemy_query: Query<(&EnemyKind, &EnemyScale, &mut Collider), With<Enemy>>,
+) {
+    for (kind, scale, mut collider) in enemy_query.iter_mut() {
+        *collider = enemy_collider(&tuning, tuning.enemy_kind(*kind), *scale);
     }
 }
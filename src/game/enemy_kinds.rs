@@ -0,0 +1,649 @@
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EnemyBounced, EnemySpawned};
+use crate::AppState;
+
+use super::arena::Arena;
+use super::difficulty::DifficultyDirector;
+use super::enemy::{
+    apply_difficulty_to_enemy_speeds, bounce_enemies_off_each_other, report_enemy_wall_bounces,
+    spawn_enemy, EnemyKind, EnemyScale,
+};
+use super::player::{MovementSystemSet, Player};
+use super::power_ups::ActivePowerUps;
+use super::rng::GameRng;
+use super::steering::{
+    validate_steering, Steering, SteeringBehavior, SteeringSystemSet, SteeringTarget,
+    WeightedBehavior,
+};
+use super::tick::{scaled_tick_seconds, Position, TimeScale, Velocity};
+use super::tuning::Tuning;
+use super::SimulationState;
+
+/// How strongly an orbiter that drifted off its circle is pulled back, per
+/// second.
+pub const ORBIT_CORRECTION: f32 = 2.;
+/// Seconds the pieces of a split enemy have to spread out before they can
+/// split again.
+pub const SPLIT_COOLDOWN: f32 = 0.5;
+
+/// Moves each enemy the way its kind does.
+pub struct EnemyKindsPlugin;
+
+impl Plugin for EnemyKindsPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_set(
+            FixedUpdate,
+            EnemyBehaviorSystemSet
+                .after(apply_difficulty_to_enemy_speeds)
+                .before(SteeringSystemSet)
+                .before(MovementSystemSet),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                (zigzag, orbit, dash).in_set(EnemyBehaviorSystemSet),
+                split_enemies
+                    .after(report_enemy_wall_bounces)
+                    .after(bounce_enemies_off_each_other),
+            )
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(SimulationState::Running)),
+        )
+        .add_systems(
+            Update,
+            apply_tuning_to_enemy_behaviors.run_if(resource_changed::<Tuning>()),
+        );
+    }
+}
+
+/// Steers the enemies every tick, before they move.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnemyBehaviorSystemSet;
+
+/// How a kind of enemy looks and moves.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyKindDefinition {
+    /// Red, green and blue the sprite's colors are multiplied by.
+    pub tint: [f32; 3],
+    /// Times the tuned enemy size.
+    pub size: f32,
+    /// Times the current enemy speed.
+    pub speed: f32,
+    /// Radius of the collider as a fraction of the size. Below 0.5 the
+    /// player can graze the enemy without being hit.
+    pub collider: f32,
+    pub behavior: EnemyBehavior,
+}
+
+/// What kinds missing from the tuning look like and do.
+pub static DEFAULT_ENEMY_KIND: EnemyKindDefinition = EnemyKindDefinition {
+    tint: [1., 1., 1.],
+    size: 1.,
+    speed: 1.,
+    collider: 0.5,
+    behavior: EnemyBehavior::Bounce,
+};
+
+impl Default for EnemyKindDefinition {
+    fn default() -> Self {
+        DEFAULT_ENEMY_KIND.clone()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum EnemyBehavior {
+    /// Keeps its heading, only walls and other enemies turn it.
+    Bounce,
+    /// Turns toward the player by at most `turn_rate` radians a second.
+    Chase { turn_rate: f32 },
+    /// Swings its heading `angle` radians to one side then the other, every
+    /// `period` seconds.
+    Zigzag { angle: f32, period: f32 },
+    /// Circles the point it spawned at, `radius` units away.
+    Orbit { radius: f32 },
+    /// Drifts for `drift` seconds, stops and flashes for `telegraph` seconds,
+    /// then lunges at where the player was for `lunge` seconds, `lunge_speed`
+    /// times as fast.
+    Dash {
+        drift: f32,
+        telegraph: f32,
+        lunge: f32,
+        lunge_speed: f32,
+    },
+    /// Breaks into `pieces` enemies `scale` times its size when it bounces,
+    /// `splits` times over.
+    Split {
+        pieces: u32,
+        splits: u32,
+        scale: f32,
+    },
+    /// Steers by the weighted `behaviors`, turning by at most `turn_rate`
+    /// radians a second and changing its velocity by at most `max_force`
+    /// units a second. Both are unlimited when left out.
+    Steer {
+        behaviors: Vec<WeightedBehavior>,
+        #[serde(default = "unlimited")]
+        max_force: f32,
+        #[serde(default = "unlimited")]
+        turn_rate: f32,
+    },
+}
+
+fn unlimited() -> f32 {
+    f32::INFINITY
+}
+
+impl EnemyKindDefinition {
+    pub fn validate(&self, name: &str, problems: &mut Vec<String>) {
+        for (field, value) in [
+            ("size", self.size),
+            ("speed", self.speed),
+            ("collider", self.collider),
+        ] {
+            require_more_than_zero(&format!("enemy_kinds.{name}"), field, value, problems);
+        }
+        let behavior_name = format!("enemy_kinds.{name}.behavior");
+        match &self.behavior {
+            EnemyBehavior::Bounce => {}
+            EnemyBehavior::Chase { turn_rate } => {
+                // Unlimited turning is fine, it just homes in straight away.
+                if turn_rate.is_nan() || *turn_rate <= 0. {
+                    problems.push(format!(
+                        "`{behavior_name}.turn_rate` must be more than zero, got {turn_rate}"
+                    ));
+                }
+            }
+            EnemyBehavior::Zigzag { angle, period } => {
+                if !angle.is_finite() || *angle < 0. {
+                    problems.push(format!(
+                        "`{behavior_name}.angle` must not be negative, got {angle}"
+                    ));
+                }
+                require_more_than_zero(&behavior_name, "period", *period, problems);
+            }
+            EnemyBehavior::Orbit { radius } => {
+                require_more_than_zero(&behavior_name, "radius", *radius, problems);
+            }
+            EnemyBehavior::Dash {
+                drift,
+                telegraph,
+                lunge,
+                lunge_speed,
+            } => {
+                for (field, value) in [
+                    ("drift", drift),
+                    ("telegraph", telegraph),
+                    ("lunge", lunge),
+                    ("lunge_speed", lunge_speed),
+                ] {
+                    require_more_than_zero(&behavior_name, field, *value, problems);
+                }
+            }
+            EnemyBehavior::Split { pieces, scale, .. } => {
+                if *pieces == 0 {
+                    problems.push(format!("`{behavior_name}.pieces` must be at least one"));
+                }
+                // The pieces have to be smaller, or splitting never ends up
+                // with anything the player can get past.
+                if scale.is_nan() || *scale <= 0. || *scale >= 1. {
+                    problems.push(format!(
+                        "`{behavior_name}.scale` must be between zero and one, got {scale}"
+                    ));
+                }
+            }
+            EnemyBehavior::Steer {
+                behaviors,
+                max_force,
+                turn_rate,
+            } => validate_steering(&behavior_name, behaviors, *max_force, *turn_rate, problems),
+        }
+    }
+}
+
+fn require_more_than_zero(name: &str, field: &str, value: f32, problems: &mut Vec<String>) {
+    if !value.is_finite() || value <= 0. {
+        problems.push(format!(
+            "`{name}.{field}` must be more than zero, got {value}"
+        ));
+    }
+}
+
+/// The default look and behavior of each kind.
+pub fn default_enemy_kinds() -> Vec<(EnemyKind, EnemyKindDefinition)> {
+    vec![
+        (EnemyKind::Bouncer, EnemyKindDefinition::default()),
+        (
+            EnemyKind::Chaser,
+            EnemyKindDefinition {
+                tint: [1., 0.6, 1.],
+                size: 0.8,
+                speed: 0.7,
+                collider: 0.45,
+                behavior: EnemyBehavior::Chase { turn_rate: 1.5 },
+            },
+        ),
+        (
+            EnemyKind::Zigzagger,
+            EnemyKindDefinition {
+                tint: [1., 1., 0.5],
+                size: 0.9,
+                speed: 1.2,
+                collider: 0.45,
+                behavior: EnemyBehavior::Zigzag {
+                    angle: 0.7,
+                    period: 0.6,
+                },
+            },
+        ),
+        (
+            EnemyKind::Orbiter,
+            EnemyKindDefinition {
+                tint: [0.6, 1., 1.],
+                size: 0.8,
+                speed: 1.,
+                collider: 0.45,
+                behavior: EnemyBehavior::Orbit { radius: 150. },
+            },
+        ),
+        (
+            EnemyKind::Dasher,
+            EnemyKindDefinition {
+                tint: [1., 0.8, 0.6],
+                size: 1.1,
+                speed: 0.5,
+                collider: 0.45,
+                behavior: EnemyBehavior::Dash {
+                    drift: 2.,
+                    telegraph: 0.6,
+                    lunge: 0.5,
+                    lunge_speed: 5.,
+                },
+            },
+        ),
+        (
+            EnemyKind::Splitter,
+            EnemyKindDefinition {
+                tint: [0.7, 1., 0.7],
+                size: 1.3,
+                speed: 0.8,
+                collider: 0.45,
+                behavior: EnemyBehavior::Split {
+                    pieces: 2,
+                    splits: 2,
+                    scale: 0.65,
+                },
+            },
+        ),
+    ]
+}
+
+#[derive(Component, Debug)]
+pub struct Zigzagger {
+    /// Seconds until the next swing.
+    pub remaining: f32,
+    /// Which way the next swing turns, 1 or -1.
+    pub side: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct Orbiter {
+    /// Picked on the first tick, far enough from the walls for the whole
+    /// circle to fit.
+    pub center: Option<Vec2>,
+    /// 1 to go counterclockwise, -1 clockwise.
+    pub direction: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DashPhase {
+    Drift,
+    Telegraph,
+    Lunge,
+}
+
+#[derive(Component, Debug)]
+pub struct Dasher {
+    pub phase: DashPhase,
+    /// Seconds left in the phase.
+    pub remaining: f32,
+    /// Where the lunge goes, picked when the telegraph starts.
+    pub direction: Vec2,
+}
+
+#[derive(Component, Debug)]
+pub struct Splitter {
+    pub splits_left: u32,
+    /// Seconds before it can split.
+    pub cooldown: f32,
+}
+
+/// How an enemy that behaves like this steers, at up to `max_speed`, for
+/// the behaviors that are made of steering ones.
+pub fn enemy_steering(behavior: &EnemyBehavior, max_speed: f32) -> Option<Steering> {
+    match behavior {
+        EnemyBehavior::Chase { turn_rate } => Some(
+            Steering::new(
+                vec![WeightedBehavior::new(
+                    SteeringBehavior::Seek(SteeringTarget::Player),
+                    1.,
+                )],
+                max_speed,
+            )
+            .with_turn_rate(*turn_rate),
+        ),
+        EnemyBehavior::Steer {
+            behaviors,
+            max_force,
+            turn_rate,
+        } => Some(
+            Steering::new(behaviors.clone(), max_speed)
+                .with_max_force(*max_force)
+                .with_turn_rate(*turn_rate),
+        ),
+        _ => None,
+    }
+}
+
+/// Adds what an enemy needs to behave the way its kind does, and turns its
+/// first heading for the behaviors that start off course.
+pub fn insert_behavior(
+    enemy: &mut EntityCommands,
+    behavior: &EnemyBehavior,
+    velocity: &mut Vec2,
+    game_rng: &mut GameRng,
+) {
+    if let Some(steering) = enemy_steering(behavior, velocity.length()) {
+        enemy.insert(steering);
+    }
+    match *behavior {
+        EnemyBehavior::Bounce | EnemyBehavior::Chase { .. } | EnemyBehavior::Steer { .. } => {}
+        EnemyBehavior::Zigzag { angle, period } => {
+            // Start on one side of the heading, so it swings evenly around it.
+            *velocity = Vec2::from_angle(-angle).rotate(*velocity);
+            enemy.insert(Zigzagger {
+                remaining: period,
+                side: 1.,
+            });
+        }
+        EnemyBehavior::Orbit { .. } => {
+            enemy.insert(Orbiter {
+                center: None,
+                direction: if game_rng.gen::<bool>() { 1. } else { -1. },
+            });
+        }
+        EnemyBehavior::Dash { drift, .. } => {
+            enemy.insert(Dasher {
+                phase: DashPhase::Drift,
+                remaining: drift,
+                direction: velocity.normalize_or_zero(),
+            });
+        }
+        EnemyBehavior::Split { splits, .. } => {
+            enemy.insert(Splitter {
+                splits_left: splits,
+                cooldown: 0.,
+            });
+        }
+    }
+}
+
+pub fn zigzag(
+    tuning: Res<Tuning>,
+    fixed_time: Res<FixedTime>,
+    mut zigzagger_query: Query<(
+        &EnemyKind,
+        &mut Zigzagger,
+        &mut Velocity,
+        Option<&TimeScale>,
+    )>,
+) {
+    for (kind, mut zigzagger, mut velocity, time_scale) in zigzagger_query.iter_mut() {
+        let EnemyBehavior::Zigzag { angle, period } = tuning.enemy_kind(*kind).behavior else {
+            continue;
+        };
+
+        zigzagger.remaining -= scaled_tick_seconds(&fixed_time, time_scale);
+        if zigzagger.remaining <= 0. {
+            zigzagger.remaining += period;
+            velocity.0 = Vec2::from_angle(2. * angle * zigzagger.side).rotate(velocity.0);
+            zigzagger.side = -zigzagger.side;
+        }
+    }
+}
+
+pub fn orbit(
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    difficulty_director: Res<DifficultyDirector>,
+    mut orbiter_query: Query<(
+        &EnemyKind,
+        &EnemyScale,
+        &mut Orbiter,
+        &Position,
+        &mut Velocity,
+    )>,
+) {
+    for (kind, scale, mut orbiter, position, mut velocity) in orbiter_query.iter_mut() {
+        let definition = tuning.enemy_kind(*kind);
+        let EnemyBehavior::Orbit { radius } = definition.behavior else {
+            continue;
+        };
+
+        let center = *orbiter.center.get_or_insert_with(|| {
+            let size = tuning.enemy_size * definition.size * scale.0;
+            let bounds = arena.bounds(size + radius * 2.);
+            if bounds.width() > 0. && bounds.height() > 0. {
+                position.0.clamp(bounds.min, bounds.max)
+            } else {
+                arena.center()
+            }
+        });
+        let offset = position.0 - center;
+        let outward = offset.try_normalize().unwrap_or(Vec2::X);
+        let speed = difficulty_director.enemy_speed * definition.speed;
+        velocity.0 = (outward.perp() * orbiter.direction * speed
+            + outward * (radius - offset.length()) * ORBIT_CORRECTION)
+            .clamp_length_max(speed);
+    }
+}
+
+pub fn dash(
+    tuning: Res<Tuning>,
+    difficulty_director: Res<DifficultyDirector>,
+    fixed_time: Res<FixedTime>,
+    player_query: Query<&Position, With<Player>>,
+    mut dasher_query: Query<(
+        &EnemyKind,
+        &mut Dasher,
+        &Position,
+        &mut Velocity,
+        Option<&TimeScale>,
+    )>,
+) {
+    let player_position = player_query.get_single().ok();
+
+    for (kind, mut dasher, position, mut velocity, time_scale) in dasher_query.iter_mut() {
+        let definition = tuning.enemy_kind(*kind);
+        let EnemyBehavior::Dash {
+            drift,
+            telegraph,
+            lunge,
+            lunge_speed,
+        } = definition.behavior
+        else {
+            continue;
+        };
+        let speed = difficulty_director.enemy_speed * definition.speed;
+
+        dasher.remaining -= scaled_tick_seconds(&fixed_time, time_scale);
+        if dasher.remaining <= 0. {
+            match dasher.phase {
+                DashPhase::Drift => {
+                    dasher.phase = DashPhase::Telegraph;
+                    dasher.remaining += telegraph;
+                    let heading = velocity.normalize_or_zero();
+                    dasher.direction = player_position
+                        .and_then(|player_position| {
+                            (player_position.0 - position.0).try_normalize()
+                        })
+                        .unwrap_or(heading);
+                }
+                DashPhase::Telegraph => {
+                    dasher.phase = DashPhase::Lunge;
+                    dasher.remaining += lunge;
+                    velocity.0 = dasher.direction;
+                }
+                DashPhase::Lunge => {
+                    dasher.phase = DashPhase::Drift;
+                    dasher.remaining += drift;
+                }
+            }
+        }
+
+        // Walls and other enemies still turn it, only its speed is kept.
+        velocity.0 = match dasher.phase {
+            DashPhase::Drift => velocity.normalize_or_zero() * speed,
+            DashPhase::Telegraph => Vec2::ZERO,
+            DashPhase::Lunge => velocity.normalize_or_zero() * speed * lunge_speed,
+        };
+        if dasher.phase == DashPhase::Drift && velocity.0 == Vec2::ZERO {
+            velocity.0 = dasher.direction * speed;
+        }
+    }
+}
+
+/// Splitters that bounced break into smaller splitters heading off in random
+/// directions.
+#[allow(clippy::too_many_arguments)]
+pub fn split_enemies(
+    mut commands: Commands,
+    mut enemy_bounced_event_reader: EventReader<EnemyBounced>,
+    tuning: Res<Tuning>,
+    difficulty_director: Res<DifficultyDirector>,
+    active_power_ups: Res<ActivePowerUps>,
+    fixed_time: Res<FixedTime>,
+    mut game_rng: ResMut<GameRng>,
+    mut splitter_query: Query<(&EnemyKind, &EnemyScale, &mut Splitter, Option<&TimeScale>)>,
+    mut enemy_spawned_event_writer: EventWriter<EnemySpawned>,
+) {
+    for (_, _, mut splitter, time_scale) in splitter_query.iter_mut() {
+        splitter.cooldown -= scaled_tick_seconds(&fixed_time, time_scale);
+    }
+
+    let mut split = Vec::new();
+    for bounce in enemy_bounced_event_reader.iter() {
+        let Ok((kind, scale, splitter, _)) = splitter_query.get(bounce.entity) else {
+            continue;
+        };
+        let EnemyBehavior::Split {
+            pieces,
+            scale: piece_scale,
+            ..
+        } = tuning.enemy_kind(*kind).behavior
+        else {
+            continue;
+        };
+        if splitter.splits_left == 0 || splitter.cooldown > 0. || split.contains(&bounce.entity) {
+            continue;
+        }
+        split.push(bounce.entity);
+
+        commands.entity(bounce.entity).despawn();
+        for _ in 0..pieces {
+            let entity = spawn_enemy(
+                &mut commands,
+                *kind,
+                bounce.position,
+                difficulty_director.enemy_speed,
+                scale.0 * piece_scale,
+                &tuning,
+                &active_power_ups,
+                &mut game_rng,
+            );
+            commands.entity(entity).insert(Splitter {
+                splits_left: splitter.splits_left - 1,
+                cooldown: SPLIT_COOLDOWN,
+            });
+            enemy_spawned_event_writer.send(EnemySpawned {
+                entity,
+                position: bounce.position,
+            });
+        }
+    }
+}
+
+/// Enemies pick up changes to the behavior of their kind. One whose kind now
+/// behaves another way swaps its behavior components and sets off again at
+/// its kind's speed, steered ones otherwise keep their top speed and take the
+/// new behaviors and limits.
+#[allow(clippy::type_complexity)]
+pub fn apply_tuning_to_enemy_behaviors(
+    mut commands: Commands,
+    tuning: Res<Tuning>,
+    difficulty_director: Res<DifficultyDirector>,
+    mut game_rng: ResMut<GameRng>,
+    mut enemy_query: Query<(
+        Entity,
+        &EnemyKind,
+        &mut Velocity,
+        Option<&mut Steering>,
+        Option<&Zigzagger>,
+        Option<&Orbiter>,
+        Option<&Dasher>,
+        Option<&Splitter>,
+    )>,
+) {
+    for (enemy, kind, mut velocity, steering, zigzagger, orbiter, dasher, splitter) in
+        enemy_query.iter_mut()
+    {
+        let definition = tuning.enemy_kind(*kind);
+        // Which of steering, zigzagging, orbiting, dashing and splitting the
+        // enemy does, and which its kind does now.
+        let behaviors = [
+            steering.is_some(),
+            zigzagger.is_some(),
+            orbiter.is_some(),
+            dasher.is_some(),
+            splitter.is_some(),
+        ];
+        let tuned_behaviors = match definition.behavior {
+            EnemyBehavior::Bounce => [false, false, false, false, false],
+            EnemyBehavior::Chase { .. } | EnemyBehavior::Steer { .. } => {
+                [true, false, false, false, false]
+            }
+            EnemyBehavior::Zigzag { .. } => [false, true, false, false, false],
+            EnemyBehavior::Orbit { .. } => [false, false, true, false, false],
+            EnemyBehavior::Dash { .. } => [false, false, false, true, false],
+            EnemyBehavior::Split { .. } => [false, false, false, false, true],
+        };
+
+        if behaviors == tuned_behaviors {
+            if let Some(mut steering) = steering {
+                if let Some(tuned) = enemy_steering(&definition.behavior, steering.max_speed) {
+                    steering.behaviors = tuned.behaviors;
+                    steering.max_force = tuned.max_force;
+                    steering.turn_rate = tuned.turn_rate;
+                }
+            }
+        } else {
+            let heading = match velocity.try_normalize() {
+                Some(heading) => heading,
+                None => Vec2::from_angle(game_rng.gen::<f32>() * std::f32::consts::TAU),
+            };
+            velocity.0 = heading * difficulty_director.enemy_speed * definition.speed;
+            let mut enemy = commands.entity(enemy);
+            enemy.remove::<(Steering, Zigzagger, Orbiter, Dasher, Splitter)>();
+            insert_behavior(
+                &mut enemy,
+                &definition.behavior,
+                &mut velocity.0,
+                &mut game_rng,
+            );
+        }
+    }
+}
@@ -0,0 +1,480 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::events::RunEnded;
+use crate::AppState;
+
+use super::mode::{Difficulty, GameMode};
+use super::replay::ReplayPlayback;
+use super::stats::{record_last_run, LastRun};
+
+/// Bumped whenever the high score file format changes.
+pub const HIGH_SCORES_VERSION: u32 = 1;
+pub const HIGH_SCORE_TABLE_SIZE: usize = 10;
+pub const HIGH_SCORES_FILE_NAME: &str = "high_scores.ron";
+
+/// Keeps the best scores of each game mode and difficulty. Replays are not
+/// new games, so their scores are left out.
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HighScores>()
+            .init_resource::<PlayerName>()
+            .add_event::<SubmitHighScore>()
+            .add_event::<HighScoreAdded>()
+            .add_systems(
+                Update,
+                (
+                    queue_high_score_on_game_over
+                        .after(record_last_run)
+                        .run_if(not(resource_exists::<ReplayPlayback>())),
+                    submit_pending_high_score.run_if(on_event::<SubmitHighScore>()),
+                ),
+            )
+            .add_systems(OnExit(AppState::GameOver), (submit_pending_high_score,));
+    }
+}
+
+/// Name the next high scores are entered under.
+#[derive(Resource, Debug, Clone)]
+pub struct PlayerName {
+    pub name: String,
+}
+
+impl Default for PlayerName {
+    fn default() -> Self {
+        Self {
+            name: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "Player".to_string()),
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HighScores {
+    pub version: u32,
+    pub entries: Vec<HighScoreEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: u32,
+    /// When the game ended, in seconds since the Unix epoch.
+    pub date: u64,
+    /// How long the game lasted, in seconds.
+    pub duration: f32,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+}
+
+impl HighScoreEntry {
+    /// The date the game ended on, as `YYYY-MM-DD` in UTC.
+    pub fn date_string(&self) -> String {
+        // Days since the epoch to a civil date, from Howard Hinnant's
+        // `civil_from_days`.
+        let days = (self.date / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl Default for HighScores {
+    fn default() -> Self {
+        Self {
+            version: HIGH_SCORES_VERSION,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl HighScores {
+    /// Best entries of a mode and difficulty, best first.
+    pub fn table(&self, mode: GameMode, difficulty: Difficulty) -> Vec<&HighScoreEntry> {
+        let mut table: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.mode == mode && entry.difficulty == difficulty)
+            .collect();
+        // Stable, so older entries stay ahead of newer ones with equal scores.
+        table.sort_by_key(|entry| Reverse(entry.score));
+        table.truncate(HIGH_SCORE_TABLE_SIZE);
+        table
+    }
+
+    /// Where a score would rank in its table, starting at zero, or `None` if
+    /// it would not make it in.
+    pub fn rank(&self, mode: GameMode, difficulty: Difficulty, score: u32) -> Option<usize> {
+        let rank = self
+            .table(mode, difficulty)
+            .iter()
+            .filter(|table_entry| table_entry.score >= score)
+            .count();
+        (score > 0 && rank < HIGH_SCORE_TABLE_SIZE).then_some(rank)
+    }
+
+    /// Whether a score would make it into its table.
+    pub fn qualifies(&self, mode: GameMode, difficulty: Difficulty, score: u32) -> bool {
+        self.rank(mode, difficulty, score).is_some()
+    }
+
+    /// Adds the entry if it qualifies and returns its rank in its table,
+    /// starting at zero. Entries pushed out of the table are dropped.
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        let (mode, difficulty) = (entry.mode, entry.difficulty);
+        let rank = self.rank(mode, difficulty, entry.score)?;
+        self.entries.push(entry);
+
+        let kept: Vec<HighScoreEntry> = self.table(mode, difficulty).into_iter().cloned().collect();
+        self.entries
+            .retain(|entry| entry.mode != mode || entry.difficulty != difficulty);
+        self.entries.extend(kept);
+        Some(rank)
+    }
+
+    /// Reads the high scores from a file. A missing file is an empty table.
+    pub fn read(path: &Path) -> Result<Self, HighScoresError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(error) => return Err(HighScoresError::Io(error)),
+        };
+
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = ron::from_str(&text).map_err(HighScoresError::Parse)?;
+        if version != HIGH_SCORES_VERSION {
+            return Err(HighScoresError::UnsupportedVersion(version));
+        }
+        ron::from_str(&text).map_err(HighScoresError::Parse)
+    }
+
+    /// Writes to a temporary file first and moves it over the old one, so a
+    /// crash halfway through never leaves a broken file behind.
+    pub fn write(&self, path: &Path) -> Result<(), HighScoresError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(HighScoresError::Serialize)?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(HighScoresError::Io)?;
+        }
+        let temporary_path = path.with_extension("ron.tmp");
+        std::fs::write(&temporary_path, text).map_err(HighScoresError::Io)?;
+        std::fs::rename(&temporary_path, path).map_err(HighScoresError::Io)
+    }
+}
+
+#[derive(Debug)]
+pub enum HighScoresError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for HighScoresError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HighScoresError::Io(error) => write!(f, "could not access the high scores: {error}"),
+            HighScoresError::Parse(error) => {
+                write!(f, "could not parse the high scores: {error}")
+            }
+            HighScoresError::Serialize(error) => {
+                write!(f, "could not write the high scores: {error}")
+            }
+            HighScoresError::UnsupportedVersion(version) => write!(
+                f,
+                "the high scores are version {version}, this game only reads version {HIGH_SCORES_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HighScoresError {}
+
+/// A score that made it into its table and waits for the player to type the
+/// name it goes under.
+#[derive(Resource, Debug, Clone)]
+pub struct PendingHighScore {
+    pub entry: HighScoreEntry,
+}
+
+/// Sent once the player is done typing their name.
+#[derive(Event)]
+pub struct SubmitHighScore;
+
+/// Sent when a score made it into its table.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HighScoreAdded {
+    pub rank: usize,
+}
+
+pub fn queue_high_score_on_game_over(
+    mut commands: Commands,
+    mut run_ended_event_reader: EventReader<RunEnded>,
+    last_run: Res<LastRun>,
+    player_name: Res<PlayerName>,
+) {
+    if run_ended_event_reader.iter().last().is_none() || last_run.high_score_rank.is_none() {
+        return;
+    }
+    commands.insert_resource(PendingHighScore {
+        entry: HighScoreEntry {
+            name: player_name.name.clone(),
+            score: last_run.score,
+            date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_secs()),
+            duration: last_run.duration,
+            seed: last_run.seed,
+            mode: last_run.mode,
+            difficulty: last_run.difficulty,
+        },
+    });
+}
+
+/// Enters the pending score under the player's name. Also runs when leaving
+/// the game over screen, so a score is never lost to a skipped name entry.
+pub fn submit_pending_high_score(
+    mut commands: Commands,
+    pending_high_score: Option<Res<PendingHighScore>>,
+    player_name: Res<PlayerName>,
+    mut high_scores: ResMut<HighScores>,
+    mut high_score_added_event_writer: EventWriter<HighScoreAdded>,
+) {
+    if let Some(pending_high_score) = pending_high_score {
+        let mut entry = pending_high_score.entry.clone();
+        entry.name = if player_name.name.trim().is_empty() {
+            PlayerName::default().name
+        } else {
+            player_name.name.clone()
+        };
+        let (mode, difficulty, score) = (entry.mode, entry.difficulty, entry.score);
+        if let Some(rank) = high_scores.insert(entry) {
+            println!(
+                "New high score #{} in {} ({}): {}",
+                rank + 1,
+                mode,
+                difficulty,
+                score
+            );
+            high_score_added_event_writer.send(HighScoreAdded { rank });
+        }
+        commands.remove_resource::<PendingHighScore>();
+    }
+}
+
+/// Saves the high scores in the user data directory whenever a score is
+/// added, and loads them back on the next launch. Left out of headless runs,
+/// so balance scripts do not fill the player's tables.
+pub struct HighScoresFilePlugin;
+
+impl Plugin for HighScoresFilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScoresFile::default())
+            .add_systems(Startup, (load_high_scores,))
+            .add_systems(
+                Update,
+                save_high_scores
+                    .after(submit_pending_high_score)
+                    .run_if(on_event::<HighScoreAdded>()),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct HighScoresFile {
+    /// `None` when there is nowhere to save to, the scores then only last
+    /// until the game is closed.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for HighScoresFile {
+    fn default() -> Self {
+        Self {
+            path: dirs::data_dir()
+                .map(|directory| directory.join("bevy_ball_game").join(HIGH_SCORES_FILE_NAME)),
+        }
+    }
+}
+
+/// A file that cannot be read is moved aside rather than overwritten, so no
+/// scores are lost to a bug or a newer version of the game.
+pub fn load_high_scores(
+    mut high_scores_file: ResMut<HighScoresFile>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    let path = match high_scores_file.path.clone() {
+        Some(path) => path,
+        None => {
+            eprintln!("no user data directory, high scores will not be saved");
+            return;
+        }
+    };
+
+    match HighScores::read(&path) {
+        Ok(loaded_high_scores) => *high_scores = loaded_high_scores,
+        Err(error) => {
+            let backup_path = path.with_extension("ron.bak");
+            eprintln!(
+                "{error}, moving {} to {}",
+                path.display(),
+                backup_path.display()
+            );
+            if let Err(error) = std::fs::rename(&path, &backup_path) {
+                eprintln!("could not move the high scores aside: {error}, they will not be saved");
+                high_scores_file.path = None;
+            }
+        }
+    }
+}
+
+pub fn save_high_scores(high_scores_file: Res<HighScoresFile>, high_scores: Res<HighScores>) {
+    if let Some(path) = &high_scores_file.path {
+        if let Err(error) = high_scores.write(path) {
+            eprintln!("{error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, mode: GameMode, difficulty: Difficulty) -> HighScoreEntry {
+        HighScoreEntry {
+            name: format!("Player {score}"),
+            score,
+            date: 0,
+            duration: 0.,
+            seed: 0,
+            mode,
+            difficulty,
+        }
+    }
+
+    fn scores(high_scores: &HighScores, mode: GameMode, difficulty: Difficulty) -> Vec<u32> {
+        high_scores
+            .table(mode, difficulty)
+            .iter()
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    #[test]
+    fn table_is_best_first() {
+        let mut high_scores = HighScores::default();
+        for score in [30, 10, 50, 20, 40] {
+            high_scores.insert(entry(score, GameMode::Endless, Difficulty::Normal));
+        }
+
+        assert_eq!(
+            scores(&high_scores, GameMode::Endless, Difficulty::Normal),
+            [50, 40, 30, 20, 10]
+        );
+    }
+
+    #[test]
+    fn equal_scores_keep_the_older_entry_first() {
+        let mut high_scores = HighScores::default();
+        let mut older = entry(10, GameMode::Endless, Difficulty::Normal);
+        older.name = "Older".to_string();
+        high_scores.insert(older);
+
+        assert_eq!(
+            high_scores.insert(entry(10, GameMode::Endless, Difficulty::Normal)),
+            Some(1)
+        );
+        assert_eq!(
+            high_scores.table(GameMode::Endless, Difficulty::Normal)[0].name,
+            "Older"
+        );
+    }
+
+    #[test]
+    fn table_is_truncated_to_its_size() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=HIGH_SCORE_TABLE_SIZE as u32 {
+            high_scores.insert(entry(score, GameMode::Endless, Difficulty::Normal));
+        }
+        high_scores.insert(entry(5, GameMode::Waves, Difficulty::Hard));
+
+        assert_eq!(
+            high_scores.insert(entry(100, GameMode::Endless, Difficulty::Normal)),
+            Some(0)
+        );
+        assert_eq!(
+            high_scores.insert(entry(1, GameMode::Endless, Difficulty::Normal)),
+            None
+        );
+
+        let table = scores(&high_scores, GameMode::Endless, Difficulty::Normal);
+        assert_eq!(table.len(), HIGH_SCORE_TABLE_SIZE);
+        assert_eq!(table.first(), Some(&100));
+        assert_eq!(table.last(), Some(&2));
+        // Pushed out entries are dropped, other tables are left alone.
+        assert_eq!(high_scores.entries.len(), HIGH_SCORE_TABLE_SIZE + 1);
+        assert_eq!(scores(&high_scores, GameMode::Waves, Difficulty::Hard), [5]);
+    }
+
+    #[test]
+    fn zero_never_qualifies() {
+        let high_scores = HighScores::default();
+
+        assert!(!high_scores.qualifies(GameMode::Endless, Difficulty::Normal, 0));
+        assert!(high_scores.qualifies(GameMode::Endless, Difficulty::Normal, 1));
+    }
+
+    #[test]
+    fn corrupt_file_is_moved_aside_and_tables_start_empty() {
+        let directory = std::env::temp_dir().join(format!(
+            "bevy_ball_game_high_scores_test_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(HIGH_SCORES_FILE_NAME);
+        std::fs::write(&path, "not a high score table").unwrap();
+
+        let mut app = App::new();
+        app.insert_resource(HighScoresFile {
+            path: Some(path.clone()),
+        })
+        .init_resource::<HighScores>()
+        .add_systems(Update, load_high_scores);
+        app.update();
+
+        let backup = std::fs::read_to_string(path.with_extension("ron.bak"));
+        let path_exists = path.exists();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(backup.unwrap(), "not a high score table");
+        assert!(!path_exists);
+        assert_eq!(*app.world.resource::<HighScores>(), HighScores::default());
+        assert_eq!(
+            app.world.resource::<HighScoresFile>().path.as_ref(),
+            Some(&path)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+use super::actions::{Action, ActionState, ActionsPlugin};
+use super::player::{MovementSystemSet, Player};
+use super::replay::ReplayPlayback;
+use super::tick::Position;
+use super::SimulationState;
+
+/// Within this distance of the pointer, the player slows down the closer it
+/// gets, so it settles on the pointer instead of overshooting it.
+pub const POINTER_ARRIVAL_DISTANCE: f32 = 100.;
+
+/// Turns actions into [`PlayerInput`] and [`TogglePause`] events, which is
+/// all gameplay reads. Stands aside while a replay is played back, so the
+/// replay can feed its own input instead.
+pub struct PlayerInputPlugin;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ActionsPlugin)
+            .init_resource::<PlayerInput>()
+            .init_resource::<ControlScheme>()
+            .init_resource::<PointerTarget>()
+            .add_event::<TogglePause>()
+            .configure_set(FixedUpdate, InputSystemSet.before(MovementSystemSet))
+            .add_systems(OnEnter(AppState::Game), (reset_player_input,))
+            .add_systems(
+                FixedUpdate,
+                (
+                    read_movement_input.run_if(resource_equals(ControlScheme::Directional)),
+                    follow_pointer.run_if(resource_equals(ControlScheme::FollowPointer)),
+                )
+                    .in_set(InputSystemSet)
+                    .run_if(not(resource_exists::<ReplayPlayback>()))
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            )
+            .add_systems(
+                Update,
+                read_pause_input
+                    .in_set(InputSystemSet)
+                    .run_if(not(resource_exists::<ReplayPlayback>()))
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct InputSystemSet;
+
+/// What the player asks for on the current tick.
+#[derive(Resource, Debug, Default)]
+pub struct PlayerInput {
+    /// Direction to move in, at most one unit long. Shorter moves slower.
+    pub movement: Vec2,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TogglePause;
+
+/// How the player steers the ball.
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ControlScheme {
+    /// Keys, buttons and sticks bound to the move actions.
+    #[default]
+    Directional,
+    /// Towards the mouse cursor or the touch point.
+    FollowPointer,
+}
+
+impl ControlScheme {
+    pub fn next(self) -> Self {
+        match self {
+            ControlScheme::Directional => ControlScheme::FollowPointer,
+            ControlScheme::FollowPointer => ControlScheme::Directional,
+        }
+    }
+}
+
+impl fmt::Display for ControlScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlScheme::Directional => write!(f, "Keys and Gamepad"),
+            ControlScheme::FollowPointer => write!(f, "Follow Pointer"),
+        }
+    }
+}
+
+/// Where the mouse cursor or touch point is in the arena, if anywhere.
+#[derive(Resource, Debug, Default)]
+pub struct PointerTarget {
+    pub position: Option<Vec2>,
+}
+
+/// Keeps a key held when the last game ended from moving the player at the
+/// start of the next one.
+pub fn reset_player_input(mut player_input: ResMut<PlayerInput>) {
+    *player_input = PlayerInput::default();
+}
+
+pub fn read_movement_input(action_state: Res<ActionState>, mut player_input: ResMut<PlayerInput>) {
+    player_input.movement = action_state.movement();
+}
+
+/// Back pauses as well, so it opens the pause menu during a game.
+pub fn read_pause_input(
+    action_state: Res<ActionState>,
+    simulation_state: Res<State<SimulationState>>,
+    mut toggle_pause_event_writer: EventWriter<TogglePause>,
+) {
+    let back_pressed = action_state.just_pressed(Action::Back)
+        && simulation_state.get() == &SimulationState::Running;
+    if action_state.just_pressed(Action::Pause) || back_pressed {
+        toggle_pause_event_writer.send(TogglePause);
+    }
+}
+
+/// Full speed towards the pointer when it is far away, slowing down within
+/// [`POINTER_ARRIVAL_DISTANCE`]. Stops when there is no pointer.
+pub fn follow_pointer(
+    pointer_target: Res<PointerTarget>,
+    player_query: Query<&Position, With<Player>>,
+    mut player_input: ResMut<PlayerInput>,
+) {
+    player_input.movement = match (pointer_target.position, player_query.get_single()) {
+        (Some(target), Ok(position)) => {
+            ((target - position.0) / POINTER_ARRIVAL_DISTANCE).clamp_length_max(1.)
+        }
+        _ => Vec2::ZERO,
+    };
+}
+
+/// Tracks the mouse cursor and touches for [`ControlScheme::FollowPointer`].
+/// Needs a window and a camera, so it is left out of headless runs.
+pub struct PointerInputPlugin;
+
+impl Plugin for PointerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (track_pointer,));
+    }
+}
+
+/// A touch wins over the cursor, a cursor outside the window is no pointer.
+pub fn track_pointer(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    touches: Res<Touches>,
+    mut pointer_target: ResMut<PointerTarget>,
+) {
+    let screen_position = touches
+        .first_pressed_position()
+        .or_else(|| window_query.get_single().ok()?.cursor_position());
+
+    pointer_target.position = screen_position.and_then(|screen_position| {
+        let (camera, camera_transform) = camera_query.get_single().ok()?;
+        camera.viewport_to_world_2d(camera_transform, screen_position)
+    });
+}
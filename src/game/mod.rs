@@ -1,30 +1,118 @@
+pub mod actions;
+pub mod arena;
+pub mod audio;
+pub mod collision;
+pub mod confinement;
+pub mod difficulty;
 pub mod enemy;
+pub mod enemy_kinds;
+pub mod high_scores;
+pub mod input;
+pub mod mode;
+pub mod music;
+pub mod particles;
 pub mod player;
+pub mod power_ups;
+pub mod replay;
+pub mod rng;
 pub mod score;
+pub mod sprites;
 pub mod star;
+pub mod stats;
+pub mod steering;
+pub mod tick;
+pub mod tuning;
+pub mod waves;
 
 use bevy::prelude::*;
 
+use arena::Arena;
+use collision::CollisionPlugin;
+use confinement::ConfinementPlugin;
+use difficulty::DifficultyPlugin;
 use enemy::EnemyPlugin;
+use enemy_kinds::EnemyKindsPlugin;
+use high_scores::HighScoresPlugin;
+use input::{InputSystemSet, PlayerInputPlugin, TogglePause};
+use mode::{Difficulty, GameMode};
 use player::PlayerPlugin;
+use power_ups::PowerUpsPlugin;
+use rng::{reset_game_rng, GameRng, GameSeed};
 use score::ScorePlugin;
 use star::StarPlugin;
+use stats::StatsPlugin;
+use steering::SteeringPlugin;
+use tick::TickPlugin;
+use tuning::Tuning;
+use waves::WavesPlugin;
 
-use crate::{events::GameOver, AppState};
+use crate::{
+    events::{
+        EnemyBounced, EnemySpawned, PlayerDied, PowerUpCollected, PowerUpExpired, RunEnded,
+        RunStarted, ShieldBroken, StarCollected, WaveCleared, WaveStarted,
+    },
+    AppState,
+};
 
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<SimulationState>()
-            .add_event::<GameOver>()
+            .init_resource::<Arena>()
+            .init_resource::<GameSeed>()
+            .init_resource::<GameRng>()
+            .init_resource::<Tuning>()
+            .init_resource::<GameMode>()
+            .init_resource::<Difficulty>()
+            .add_event::<RunStarted>()
+            .add_event::<RunEnded>()
+            .add_event::<PlayerDied>()
+            .add_event::<StarCollected>()
+            .add_event::<EnemySpawned>()
+            .add_event::<EnemyBounced>()
+            .add_event::<WaveStarted>()
+            .add_event::<WaveCleared>()
+            .add_event::<PowerUpCollected>()
+            .add_event::<PowerUpExpired>()
+            .add_event::<ShieldBroken>()
+            .add_event::<RestartGame>()
+            .add_plugins(TickPlugin)
+            .add_plugins(PlayerInputPlugin)
+            .add_plugins(CollisionPlugin)
+            .add_plugins(ConfinementPlugin)
             .add_plugins(PlayerPlugin)
+            .add_plugins(DifficultyPlugin)
             .add_plugins(EnemyPlugin)
+            .add_plugins(EnemyKindsPlugin)
+            .add_plugins(SteeringPlugin)
             .add_plugins(StarPlugin)
+            .add_plugins(PowerUpsPlugin)
+            .add_plugins(WavesPlugin)
             .add_plugins(ScorePlugin)
-            .add_systems(OnEnter(AppState::Game), (pause_simulation,))
+            .add_plugins(StatsPlugin)
+            .add_plugins(HighScoresPlugin)
+            .add_systems(
+                OnEnter(AppState::Game),
+                (
+                    pause_simulation,
+                    reset_game_rng,
+                    start_run.after(reset_game_rng),
+                ),
+            )
             .add_systems(OnExit(AppState::Game), (resume_simulation,))
-            .add_systems(Update, toggle_simulation.run_if(in_state(AppState::Game)));
+            .add_systems(
+                Update,
+                toggle_simulation
+                    .after(InputSystemSet)
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                Update,
+                restart_game
+                    .run_if(on_event::<RestartGame>())
+                    .run_if(in_state(AppState::Game)),
+            );
     }
 }
 
@@ -35,6 +123,23 @@ pub enum SimulationState {
     Paused,
 }
 
+/// Ends the current game and starts a new one without leaving the game state.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RestartGame;
+
+/// Entering the state the game is already in does nothing, so the systems
+/// that end and start a game are run by hand instead.
+pub fn restart_game(world: &mut World) {
+    world.run_schedule(OnExit(AppState::Game));
+    world.run_schedule(OnEnter(AppState::Game));
+}
+
+pub fn start_run(game_rng: Res<GameRng>, mut run_started_event_writer: EventWriter<RunStarted>) {
+    run_started_event_writer.send(RunStarted {
+        seed: game_rng.seed,
+    });
+}
+
 pub fn pause_simulation(mut next_simulation_state: ResMut<NextState<SimulationState>>) {
     next_simulation_state.set(SimulationState::Paused);
 }
@@ -44,11 +149,11 @@ pub fn resume_simulation(mut next_simulation_state: ResMut<NextState<SimulationS
 }
 
 pub fn toggle_simulation(
-    keyboard_input: Res<Input<KeyCode>>,
+    mut toggle_pause_event_reader: EventReader<TogglePause>,
     simulation_state: Res<State<SimulationState>>,
     mut next_simulation_state: ResMut<NextState<SimulationState>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
+    if toggle_pause_event_reader.iter().last().is_some() {
         if simulation_state.get() == &SimulationState::Running {
             next_simulation_state.set(SimulationState::Paused);
             println!("entered simulation state paused");
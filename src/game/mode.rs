@@ -0,0 +1,80 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Rules the next games are played with. Each has its own high score tables.
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Enemies keep coming until the player is hit.
+    #[default]
+    Endless,
+    /// Authored waves of enemies from `assets/game.waves.ron`, then generated ones
+    /// once they run out.
+    Waves,
+}
+
+impl GameMode {
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Endless => GameMode::Waves,
+            GameMode::Waves => GameMode::Endless,
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+}
+
+impl Difficulty {
+    /// The next harder difficulty, wrapping around to the easiest.
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Insane,
+            Difficulty::Insane => Difficulty::Easy,
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameMode::Endless => write!(f, "Endless"),
+            GameMode::Waves => write!(f, "Waves"),
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "endless" => Ok(GameMode::Endless),
+            "waves" => Ok(GameMode::Waves),
+            _ => Err(format!(
+                "unknown game mode `{name}`, expected endless or waves"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Insane => write!(f, "Insane"),
+        }
+    }
+}
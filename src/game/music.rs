@@ -0,0 +1,359 @@
+use std::fmt;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+use super::audio::{AudioChannel, AudioSettings};
+use super::SimulationState;
+
+pub const MUSIC_MANIFEST_PATH: &str = "game.music.ron";
+
+/// Plays a looping track for each app state, crossfading between them and
+/// turning the music down while the game is paused. Left out when running
+/// without an audio device.
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<MusicManifest>()
+            .init_asset_loader::<MusicManifestLoader>()
+            .init_resource::<MusicManifest>()
+            .init_resource::<MusicDuck>()
+            .add_systems(Startup, (load_music_manifest,))
+            .add_systems(
+                Update,
+                (
+                    apply_loaded_music_manifest,
+                    play_state_music.run_if(
+                        state_changed::<AppState>().or_else(resource_changed::<MusicManifest>()),
+                    ),
+                    duck_music_while_paused,
+                    fade_music,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Which track plays in each app state, loaded from `assets/game.music.ron`.
+/// Falls back to the built in defaults when the file is not available. A
+/// track whose file is missing is silent, the game plays on without it.
+#[derive(Resource, Serialize, Deserialize, TypeUuid, TypePath, Debug, Clone, PartialEq)]
+#[uuid = "0c6f3c52-7d8e-4a5b-9f1e-2b4d6a8c0e13"]
+#[serde(default, deny_unknown_fields)]
+pub struct MusicManifest {
+    pub main_menu: Option<MusicTrack>,
+    pub game: Option<MusicTrack>,
+    pub game_over: Option<MusicTrack>,
+    /// Seconds one track takes to fade into the next.
+    pub crossfade_duration: f32,
+    /// Music volume while the game is paused, relative to playing.
+    pub paused_volume: f32,
+    /// Seconds the music takes to turn down on pause and back up on resume.
+    pub duck_duration: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MusicTrack {
+    /// Path of the sound file, from the assets folder.
+    pub path: String,
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+}
+
+fn full_volume() -> f32 {
+    1.
+}
+
+impl Default for MusicManifest {
+    fn default() -> Self {
+        Self {
+            main_menu: Some(MusicTrack {
+                path: "audio/pluck_001.ogg".to_string(),
+                volume: 0.5,
+            }),
+            game: Some(MusicTrack {
+                path: "audio/pluck_002.ogg".to_string(),
+                volume: 0.4,
+            }),
+            game_over: Some(MusicTrack {
+                path: "audio/pluck_001.ogg".to_string(),
+                volume: 0.3,
+            }),
+            crossfade_duration: 1.5,
+            paused_volume: 0.3,
+            duck_duration: 0.3,
+        }
+    }
+}
+
+impl MusicManifest {
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, MusicManifestError> {
+        let manifest: MusicManifest =
+            ron::de::from_bytes(bytes).map_err(MusicManifestError::Parse)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> Result<(), MusicManifestError> {
+        let mut problems = Vec::new();
+
+        for (name, value) in [
+            ("crossfade_duration", self.crossfade_duration),
+            ("duck_duration", self.duck_duration),
+        ] {
+            if !value.is_finite() || value < 0. {
+                problems.push(format!("{name} must not be negative, got {value}"));
+            }
+        }
+        if !(0. ..=1.).contains(&self.paused_volume) {
+            problems.push(format!(
+                "paused_volume must be between 0 and 1, got {}",
+                self.paused_volume
+            ));
+        }
+        for (name, track) in [
+            ("main_menu", &self.main_menu),
+            ("game", &self.game),
+            ("game_over", &self.game_over),
+        ] {
+            if let Some(track) = track {
+                if !(0. ..=1.).contains(&track.volume) {
+                    problems.push(format!(
+                        "{name} volume must be between 0 and 1, got {}",
+                        track.volume
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(MusicManifestError::Invalid(problems))
+        }
+    }
+
+    pub fn track(&self, app_state: &AppState) -> Option<&MusicTrack> {
+        match app_state {
+            AppState::MainMenu => self.main_menu.as_ref(),
+            AppState::Game => self.game.as_ref(),
+            AppState::GameOver => self.game_over.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MusicManifestError {
+    Parse(ron::error::SpannedError),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for MusicManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MusicManifestError::Parse(error) => {
+                write!(f, "could not parse {MUSIC_MANIFEST_PATH}: {error}")
+            }
+            MusicManifestError::Invalid(problems) => {
+                write!(f, "invalid values in {MUSIC_MANIFEST_PATH}:")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MusicManifestError {}
+
+#[derive(Default)]
+pub struct MusicManifestLoader;
+
+impl AssetLoader for MusicManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest = MusicManifest::from_ron(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["music.ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct MusicManifestHandle {
+    pub handle: Handle<MusicManifest>,
+}
+
+pub fn load_music_manifest(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(MusicManifestHandle {
+        handle: asset_server.load(MUSIC_MANIFEST_PATH),
+    });
+}
+
+pub fn apply_loaded_music_manifest(
+    mut manifest_event_reader: EventReader<AssetEvent<MusicManifest>>,
+    manifest_assets: Res<Assets<MusicManifest>>,
+    mut music_manifest: ResMut<MusicManifest>,
+) {
+    for event in manifest_event_reader.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(loaded_manifest) = manifest_assets.get(handle) {
+                if *music_manifest != *loaded_manifest {
+                    *music_manifest = loaded_manifest.clone();
+                    println!("Applied {}", MUSIC_MANIFEST_PATH);
+                }
+            }
+        }
+    }
+}
+
+/// A music track that is playing, or fading in or out.
+#[derive(Component, Debug)]
+pub struct MusicVoice {
+    pub path: String,
+    /// Volume of the track itself, before fading and the settings.
+    pub volume: f32,
+    /// How far the track has faded in, from 0 to 1.
+    pub fade: f32,
+    /// Fading out to be despawned, rather than in.
+    pub fading_out: bool,
+}
+
+/// How far the music is turned down for the pause, 1 while playing.
+#[derive(Resource, Debug)]
+pub struct MusicDuck {
+    pub volume: f32,
+}
+
+impl Default for MusicDuck {
+    fn default() -> Self {
+        Self { volume: 1. }
+    }
+}
+
+/// Fades in the track of the current state and fades out every other. A
+/// track shared by both states keeps playing through the change.
+pub fn play_state_music(
+    mut commands: Commands,
+    app_state: Res<State<AppState>>,
+    music_manifest: Res<MusicManifest>,
+    asset_server: Res<AssetServer>,
+    mut voice_query: Query<&mut MusicVoice>,
+) {
+    let track = music_manifest.track(app_state.get());
+
+    let mut playing = false;
+    for mut voice in voice_query.iter_mut() {
+        match track {
+            Some(track) if track.path == voice.path && !playing => {
+                voice.fading_out = false;
+                voice.volume = track.volume;
+                playing = true;
+            }
+            _ => voice.fading_out = true,
+        }
+    }
+
+    if let (Some(track), false) = (track, playing) {
+        commands.spawn((
+            AudioBundle {
+                source: asset_server.load(&track.path),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Loop,
+                    volume: Volume::new_relative(0.),
+                    ..Default::default()
+                },
+            },
+            MusicVoice {
+                path: track.path.clone(),
+                volume: track.volume,
+                fade: 0.,
+                fading_out: false,
+            },
+        ));
+    }
+}
+
+pub fn duck_music_while_paused(
+    app_state: Res<State<AppState>>,
+    simulation_state: Res<State<SimulationState>>,
+    music_manifest: Res<MusicManifest>,
+    time: Res<Time>,
+    mut music_duck: ResMut<MusicDuck>,
+) {
+    let paused =
+        app_state.get() == &AppState::Game && simulation_state.get() == &SimulationState::Paused;
+    let target = if paused {
+        music_manifest.paused_volume
+    } else {
+        1.
+    };
+    if music_duck.volume == target {
+        return;
+    }
+
+    let step = fade_step(time.delta_seconds(), music_manifest.duck_duration);
+    music_duck.volume = if music_duck.volume < target {
+        (music_duck.volume + step).min(target)
+    } else {
+        (music_duck.volume - step).max(target)
+    };
+}
+
+/// Moves every track along its fade and sets the volumes it plays at. A
+/// track that has faded out is stopped.
+pub fn fade_music(
+    mut commands: Commands,
+    music_manifest: Res<MusicManifest>,
+    music_duck: Res<MusicDuck>,
+    audio_settings: Res<AudioSettings>,
+    time: Res<Time>,
+    mut voice_query: Query<(Entity, &mut MusicVoice, Option<&AudioSink>)>,
+) {
+    let step = fade_step(time.delta_seconds(), music_manifest.crossfade_duration);
+    let volume = music_duck.volume * audio_settings.volume(AudioChannel::Music);
+
+    for (entity, mut voice, sink) in voice_query.iter_mut() {
+        if voice.fading_out {
+            voice.fade = (voice.fade - step).max(0.);
+            if voice.fade == 0. {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        } else {
+            voice.fade = (voice.fade + step).min(1.);
+        }
+
+        // The sink only shows up once the file has loaded, and never if it
+        // is missing.
+        if let Some(sink) = sink {
+            sink.set_volume(voice.volume * voice.fade * volume);
+        }
+    }
+}
+
+/// How much of a fade lasting `duration` seconds happens in `delta` seconds.
+fn fade_step(delta: f32, duration: f32) -> f32 {
+    if duration > 0. {
+        delta / duration
+    } else {
+        1.
+    }
+}
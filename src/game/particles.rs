@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::events::{PlayerDied, ShieldBroken, StarCollected};
+use crate::AppState;
+
+use super::SimulationState;
+
+pub const STAR_PARTICLE_COLOR: Color = Color::rgb(1., 0.85, 0.2);
+pub const PLAYER_PARTICLE_COLOR: Color = Color::rgb(0.9, 0.2, 0.2);
+pub const SHIELD_PARTICLE_COLOR: Color = Color::rgb(0.5, 1., 1.);
+pub const PARTICLE_SIZE: f32 = 6.;
+
+/// Bursts of sparks where stars are collected, the player is hit and shields
+/// break. Left out when running without a renderer.
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnExit(AppState::Game), (despawn_particles,))
+            .add_systems(
+                Update,
+                (spawn_gameplay_particles, update_particles)
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            );
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Particle {
+    pub velocity: Vec2,
+    /// Seconds left before it disappears.
+    pub lifetime: f32,
+    pub max_lifetime: f32,
+}
+
+pub fn spawn_particle_burst(commands: &mut Commands, position: Vec2, color: Color, count: usize) {
+    // Particles are only for show, they must not take from the game RNG.
+    let mut rng = rand::thread_rng();
+    for _ in 0..count {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = rng.gen_range(60.0..240.);
+        let lifetime = rng.gen_range(0.3..0.7);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, 1.),
+                ..Default::default()
+            },
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                lifetime,
+                max_lifetime: lifetime,
+            },
+        ));
+    }
+}
+
+pub fn spawn_gameplay_particles(
+    mut commands: Commands,
+    mut star_collected_event_reader: EventReader<StarCollected>,
+    mut player_died_event_reader: EventReader<PlayerDied>,
+    mut shield_broken_event_reader: EventReader<ShieldBroken>,
+) {
+    for star_collected in star_collected_event_reader.iter() {
+        spawn_particle_burst(
+            &mut commands,
+            star_collected.position,
+            STAR_PARTICLE_COLOR,
+            12,
+        );
+    }
+    for player_died in player_died_event_reader.iter() {
+        spawn_particle_burst(
+            &mut commands,
+            player_died.position,
+            PLAYER_PARTICLE_COLOR,
+            32,
+        );
+    }
+    for shield_broken in shield_broken_event_reader.iter() {
+        spawn_particle_burst(
+            &mut commands,
+            shield_broken.position,
+            SHIELD_PARTICLE_COLOR,
+            24,
+        );
+    }
+}
+
+pub fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in particle_query.iter_mut() {
+        particle.lifetime -= time.delta_seconds();
+        if particle.lifetime <= 0. {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        sprite
+            .color
+            .set_a(particle.lifetime / particle.max_lifetime);
+    }
+}
+
+pub fn despawn_particles(mut commands: Commands, particle_query: Query<Entity, With<Particle>>) {
+    for entity in particle_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -1,18 +1,24 @@
-use bevy::audio::PlaybackMode;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
 
-use crate::events::GameOver;
+use crate::events::{PlayerDied, RunEnded, ShieldBroken, StarCollected};
 use crate::AppState;
 
-use super::enemy::{Enemy, ENEMY_SIZE};
+use super::arena::Arena;
+use super::collision::{
+    ActiveCollisions, Collider, CollisionLayers, CollisionStarted, CollisionSystemSet, ENEMY_LAYER,
+    PLAYER_LAYER, POWER_UP_LAYER, STAR_LAYER,
+};
+use super::confinement::ConfinedToArena;
+use super::difficulty::DifficultyDirector;
+use super::enemy::Enemy;
+use super::input::PlayerInput;
+use super::power_ups::ActivePowerUps;
 use super::score::Score;
-use super::star::{Star, STAR_SIZE};
+use super::star::Star;
+use super::tick::{tick_seconds, Position, PositionBundle};
+use super::tuning::Tuning;
 use super::SimulationState;
 
-pub const PLAYER_SPEED: f32 = 500.;
-pub const PLAYER_SIZE: f32 = 64.;
-
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct MovementSystemSet;
 
@@ -23,19 +29,23 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.configure_set(Update, ConfinementSystemSet.after(MovementSystemSet))
+        app.configure_set(FixedUpdate, ConfinementSystemSet.after(MovementSystemSet))
             .add_systems(OnEnter(AppState::Game), (spawn_player,))
             .add_systems(OnExit(AppState::Game), (despawn_player,))
             .add_systems(
-                Update,
+                FixedUpdate,
                 (
                     player_movement.in_set(MovementSystemSet),
-                    confine_player_movement.in_set(ConfinementSystemSet),
-                    enemy_hit_player,
-                    player_hit_star,
+                    enemy_hit_player.after(CollisionSystemSet),
+                    end_run_on_player_death.after(enemy_hit_player),
+                    player_hit_star.after(CollisionSystemSet),
                 )
                     .run_if(in_state(AppState::Game))
                     .run_if(in_state(SimulationState::Running)),
+            )
+            .add_systems(
+                Update,
+                apply_tuning_to_player_collider.run_if(resource_changed::<Tuning>()),
             );
     }
 }
@@ -43,19 +53,18 @@ impl Plugin for PlayerPlugin {
 #[derive(Component)]
 pub struct Player {}
 
-pub fn spawn_player(
-    mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
-) {
-    let window = window_query.get_single().unwrap();
+pub fn player_collider(tuning: &Tuning) -> Collider {
+    Collider::circle(
+        tuning.player_size / 2.,
+        CollisionLayers::new(PLAYER_LAYER, ENEMY_LAYER | STAR_LAYER | POWER_UP_LAYER),
+    )
+}
 
+pub fn spawn_player(mut commands: Commands, arena: Res<Arena>, tuning: Res<Tuning>) {
     commands.spawn((
-        SpriteBundle {
-            transform: Transform::from_xyz(window.width() / 2., window.height() / 2., 0.),
-            texture: asset_server.load("sprites/ball_blue_large.png"),
-            ..Default::default()
-        },
+        PositionBundle::new(arena.center()),
+        player_collider(&tuning),
+        ConfinedToArena::Clamp,
         Player {},
     ));
 }
@@ -67,120 +76,106 @@ pub fn despawn_player(mut commands: Commands, player_query: Query<Entity, With<P
 }
 
 pub fn player_movement(
-    keyboard_input: Res<Input<KeyCode>>,
-    time: Res<Time>,
-    mut player_query: Query<&mut Transform, With<Player>>,
+    player_input: Res<PlayerInput>,
+    fixed_time: Res<FixedTime>,
+    tuning: Res<Tuning>,
+    active_power_ups: Res<ActivePowerUps>,
+    mut player_query: Query<&mut Position, With<Player>>,
 ) {
-    if let Ok(mut transform) = player_query.get_single_mut() {
-        let mut direction = Vec3::ZERO;
-
-        if keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W) {
-            direction += Vec3::new(0., 1., 0.)
-        }
-        if keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S) {
-            direction += Vec3::new(0., -1., 0.)
-        }
-        if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
-            direction += Vec3::new(-1., 0., 0.)
-        }
-        if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
-            direction += Vec3::new(1., 0., 0.)
-        }
-
-        if direction.length() > 0. {
-            direction = direction.normalize();
-        }
-
-        transform.translation += direction * PLAYER_SPEED * time.delta_seconds();
+    if let Ok(mut position) = player_query.get_single_mut() {
+        let speed =
+            tuning.player_speed * active_power_ups.player_speed_multiplier(&tuning.power_ups);
+        position.0 += player_input.movement * speed * tick_seconds(&fixed_time);
     }
 }
 
-pub fn confine_player_movement(
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut player_query: Query<&mut Transform, With<Player>>,
+/// Kills the player, unless a shield takes the hit or the grace after one is
+/// still running. Looks at every enemy touching the player, not only the ones
+/// that just started to, so a contact made while the game was paused or
+/// restarting, or that outlasted the grace, still counts.
+#[allow(clippy::too_many_arguments)]
+pub fn enemy_hit_player(
+    mut commands: Commands,
+    tuning: Res<Tuning>,
+    active_collisions: Res<ActiveCollisions>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    mut shield_broken_event_writer: EventWriter<ShieldBroken>,
+    mut player_died_event_writer: EventWriter<PlayerDied>,
+    player_query: Query<&Position, With<Player>>,
+    enemy_query: Query<(), With<Enemy>>,
 ) {
-    if let Ok(mut transform) = player_query.get_single_mut() {
-        let window = window_query.get_single().unwrap();
-
-        let half_player_size = PLAYER_SIZE / 2.;
-
-        let x_min = 0. + half_player_size;
-        let x_max = window.width() - half_player_size;
-        let y_min = 0. + half_player_size;
-        let y_max = window.height() - half_player_size;
-
-        let mut translation = transform.translation;
+    if active_power_ups.is_invulnerable() {
+        return;
+    }
 
-        if translation.x < x_min {
-            translation.x = x_min;
-        } else if translation.x > x_max {
-            translation.x = x_max;
+    let hit = active_collisions
+        .between(
+            |entity| player_query.contains(entity),
+            |entity| enemy_query.contains(entity),
+        )
+        .next();
+
+    if let Some((player_entity, _)) = hit {
+        if active_power_ups.break_shield(&tuning.power_ups) {
+            if let Ok(position) = player_query.get(player_entity) {
+                shield_broken_event_writer.send(ShieldBroken {
+                    position: position.0,
+                });
+            }
+            return;
         }
-        if translation.y < y_min {
-            translation.y = y_min;
-        } else if translation.y > y_max {
-            translation.y = y_max;
+        if let Ok(position) = player_query.get(player_entity) {
+            player_died_event_writer.send(PlayerDied {
+                position: position.0,
+            });
         }
-
-        transform.translation = translation;
+        commands.entity(player_entity).despawn();
     }
 }
 
-pub fn enemy_hit_player(
-    mut commands: Commands,
-    mut game_over_event_writer: EventWriter<GameOver>,
-    player_query: Query<(Entity, &Transform), With<Player>>,
-    enemy_query: Query<&Transform, With<Enemy>>,
-    asset_server: Res<AssetServer>,
+/// The player has a single life, so the game ends with it.
+pub fn end_run_on_player_death(
+    mut player_died_event_reader: EventReader<PlayerDied>,
+    mut run_ended_event_writer: EventWriter<RunEnded>,
     score: Res<Score>,
 ) {
-    let touch_distance = ENEMY_SIZE / 2. + PLAYER_SIZE / 2.;
-
-    if let Ok((player_entity, player_transform)) = player_query.get_single() {
-        enemy_query.for_each(|enemy_transform| {
-            let distance = enemy_transform
-                .translation
-                .distance(player_transform.translation);
-            if distance <= touch_distance {
-                commands.spawn(AudioBundle {
-                    source: asset_server.load("audio/explosionCrunch_000.ogg"),
-                    settings: PlaybackSettings {
-                        mode: PlaybackMode::Despawn,
-                        ..Default::default()
-                    },
-                });
-                commands.entity(player_entity).despawn();
-                game_over_event_writer.send(GameOver { score: score.value });
-            }
-        })
+    if player_died_event_reader.iter().last().is_some() {
+        run_ended_event_writer.send(RunEnded { score: score.value });
     }
 }
 
 pub fn player_hit_star(
     mut commands: Commands,
-    player_query: Query<&Transform, With<Player>>,
-    star_query: Query<(Entity, &Transform), With<Star>>,
+    mut collision_event_reader: EventReader<CollisionStarted>,
+    player_query: Query<(), With<Player>>,
+    star_query: Query<&Position, With<Star>>,
     mut score: ResMut<Score>,
-    asset_server: Res<AssetServer>,
+    difficulty_director: Res<DifficultyDirector>,
+    mut star_collected_event_writer: EventWriter<StarCollected>,
 ) {
-    let touch_distance = STAR_SIZE / 2. + PLAYER_SIZE / 2.;
-
-    if let Ok(player_transform) = player_query.get_single() {
-        star_query.for_each(|(star_entity, star_transform)| {
-            let distance = star_transform
-                .translation
-                .distance(player_transform.translation);
-            if distance <= touch_distance {
-                score.value += 1;
-                commands.spawn(AudioBundle {
-                    source: asset_server.load("audio/laserLarge_000.ogg"),
-                    settings: PlaybackSettings {
-                        mode: PlaybackMode::Despawn,
-                        ..Default::default()
-                    },
+    for collision in collision_event_reader.iter() {
+        if let Some((_, star_entity)) = collision.between(
+            |entity| player_query.contains(entity),
+            |entity| star_query.contains(entity),
+        ) {
+            score.value += difficulty_director.score_multiplier;
+            score.stars += 1;
+            if let Ok(position) = star_query.get(star_entity) {
+                star_collected_event_writer.send(StarCollected {
+                    position: position.0,
+                    score: score.value,
                 });
-                commands.entity(star_entity).despawn();
             }
-        })
+            commands.entity(star_entity).despawn();
+        }
+    }
+}
+
+pub fn apply_tuning_to_player_collider(
+    tuning: Res<Tuning>,
+    mut player_query: Query<&mut Collider, With<Player>>,
+) {
+    for mut collider in player_query.iter_mut() {
+        *collider = player_collider(&tuning);
     }
 }
@@ -0,0 +1,439 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{PowerUpCollected, PowerUpExpired};
+use crate::AppState;
+
+use super::arena::Arena;
+use super::collision::{
+    Collider, CollisionLayers, CollisionStarted, CollisionSystemSet, PLAYER_LAYER, POWER_UP_LAYER,
+};
+use super::confinement::ConfinedToArena;
+use super::enemy::Enemy;
+use super::enemy_kinds::EnemyBehaviorSystemSet;
+use super::player::{MovementSystemSet, Player};
+use super::rng::GameRng;
+use super::star::{spawn_stars_over_time, Star, StarSpawnTimer};
+use super::steering::SteeringSystemSet;
+use super::tick::{tick_seconds, Position, PositionBundle, TimeScale};
+use super::tuning::Tuning;
+use super::SimulationState;
+
+/// Rare pickups that show up with the stars and give the player a timed
+/// boost: a shield, more speed, slower enemies or a star magnet.
+pub struct PowerUpsPlugin;
+
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActivePowerUps>()
+            .add_systems(OnEnter(AppState::Game), (reset_active_power_ups,))
+            .add_systems(OnExit(AppState::Game), (despawn_power_ups,))
+            .add_systems(
+                FixedUpdate,
+                (
+                    spawn_power_ups_over_time.after(spawn_stars_over_time),
+                    (tick_active_power_ups, tick_power_up_lifetimes),
+                    apply_slow_mo_to_enemies
+                        .after(tick_active_power_ups)
+                        .before(EnemyBehaviorSystemSet)
+                        .before(SteeringSystemSet)
+                        .before(MovementSystemSet),
+                    pull_stars_to_player.in_set(MovementSystemSet),
+                    player_hit_power_up.after(CollisionSystemSet),
+                )
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            )
+            .add_systems(
+                Update,
+                apply_tuning_to_power_up_colliders.run_if(resource_changed::<Tuning>()),
+            );
+    }
+}
+
+#[derive(
+    Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum PowerUpKind {
+    /// Absorbs the next enemy hit.
+    Shield,
+    /// The player moves faster.
+    SpeedBoost,
+    /// Enemies move and act slower.
+    SlowMo,
+    /// Stars nearby fly to the player.
+    Magnet,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::Shield,
+        PowerUpKind::SpeedBoost,
+        PowerUpKind::SlowMo,
+        PowerUpKind::Magnet,
+    ];
+}
+
+impl fmt::Display for PowerUpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::SpeedBoost => "Speed",
+            PowerUpKind::SlowMo => "Slow-mo",
+            PowerUpKind::Magnet => "Magnet",
+        })
+    }
+}
+
+/// What picking up a power-up that is still running does.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PowerUpStacking {
+    /// Starts its time over.
+    Refresh,
+    /// Adds its duration to the time left, up to `max` seconds.
+    Extend { max: f32 },
+}
+
+/// How long a power-up lasts once picked up, in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PowerUpTiming {
+    pub duration: f32,
+    pub stacking: PowerUpStacking,
+}
+
+/// Power-up values in the tuning. Different power-ups run side by side, each
+/// stacks with itself by its own rule.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerUpTuning {
+    /// Chance of a power-up showing up with each star spawned over time.
+    pub chance: f32,
+    pub size: f32,
+    /// Seconds a power-up waits to be picked up before it goes away.
+    pub lifetime: f32,
+    pub shield: PowerUpTiming,
+    /// Seconds the player cannot be hit after the shield breaks, to get clear
+    /// of the enemies that broke it.
+    pub shield_grace: f32,
+    pub speed_boost: PowerUpTiming,
+    /// Times the player speed.
+    pub speed_boost_multiplier: f32,
+    pub slow_mo: PowerUpTiming,
+    /// How fast time passes for the enemies, 1 being normal.
+    pub slow_mo_time_scale: f32,
+    pub magnet: PowerUpTiming,
+    /// How far from the player stars are pulled from.
+    pub magnet_radius: f32,
+    pub magnet_speed: f32,
+}
+
+impl Default for PowerUpTuning {
+    fn default() -> Self {
+        Self {
+            chance: 0.08,
+            size: 40.,
+            lifetime: 8.,
+            shield: PowerUpTiming {
+                duration: 10.,
+                stacking: PowerUpStacking::Refresh,
+            },
+            shield_grace: 1.,
+            speed_boost: PowerUpTiming {
+                duration: 5.,
+                stacking: PowerUpStacking::Extend { max: 12. },
+            },
+            speed_boost_multiplier: 1.6,
+            slow_mo: PowerUpTiming {
+                duration: 4.,
+                stacking: PowerUpStacking::Refresh,
+            },
+            slow_mo_time_scale: 0.4,
+            magnet: PowerUpTiming {
+                duration: 6.,
+                stacking: PowerUpStacking::Extend { max: 15. },
+            },
+            magnet_radius: 250.,
+            magnet_speed: 600.,
+        }
+    }
+}
+
+impl PowerUpTuning {
+    pub fn timing(&self, kind: PowerUpKind) -> PowerUpTiming {
+        match kind {
+            PowerUpKind::Shield => self.shield,
+            PowerUpKind::SpeedBoost => self.speed_boost,
+            PowerUpKind::SlowMo => self.slow_mo,
+            PowerUpKind::Magnet => self.magnet,
+        }
+    }
+
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        if !(0. ..=1.).contains(&self.chance) {
+            problems.push(format!(
+                "`power_ups.chance` must be from 0 to 1, got {}",
+                self.chance
+            ));
+        }
+        for (name, value) in [
+            ("size", self.size),
+            ("lifetime", self.lifetime),
+            ("shield_grace", self.shield_grace),
+            ("speed_boost_multiplier", self.speed_boost_multiplier),
+            ("slow_mo_time_scale", self.slow_mo_time_scale),
+            ("magnet_radius", self.magnet_radius),
+            ("magnet_speed", self.magnet_speed),
+        ] {
+            if !value.is_finite() || value <= 0. {
+                problems.push(format!(
+                    "`power_ups.{name}` must be more than zero, got {value}"
+                ));
+            }
+        }
+        for (name, timing) in [
+            ("shield", self.shield),
+            ("speed_boost", self.speed_boost),
+            ("slow_mo", self.slow_mo),
+            ("magnet", self.magnet),
+        ] {
+            if !timing.duration.is_finite() || timing.duration <= 0. {
+                problems.push(format!(
+                    "`power_ups.{name}.duration` must be more than zero, got {}",
+                    timing.duration
+                ));
+            }
+            if let PowerUpStacking::Extend { max } = timing.stacking {
+                if max.is_nan() || max < timing.duration {
+                    problems.push(format!(
+                        "`power_ups.{name}.stacking` max must be at least the duration, got {max}"
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// A power-up waiting in the arena to be picked up.
+#[derive(Component, Debug)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    /// Seconds before it goes away.
+    pub remaining: f32,
+}
+
+/// Seconds left on each power-up the player has running.
+#[derive(Resource, Debug, Default)]
+pub struct ActivePowerUps {
+    pub remaining: BTreeMap<PowerUpKind, f32>,
+    /// Seconds left in which the player cannot be hit, after a shield broke.
+    pub invulnerable: f32,
+}
+
+impl ActivePowerUps {
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.remaining.contains_key(&kind)
+    }
+
+    /// Starts a power-up, or stacks it onto the one already running.
+    pub fn start(&mut self, kind: PowerUpKind, timing: PowerUpTiming) {
+        let remaining = self.remaining.entry(kind).or_insert(0.);
+        *remaining = match timing.stacking {
+            PowerUpStacking::Refresh => timing.duration,
+            PowerUpStacking::Extend { max } => (*remaining + timing.duration).min(max),
+        };
+    }
+
+    /// Ends a power-up early, returning whether it was running.
+    pub fn end(&mut self, kind: PowerUpKind) -> bool {
+        self.remaining.remove(&kind).is_some()
+    }
+
+    /// Breaks the shield, returning whether there was one. Every enemy
+    /// touching the player on that tick is stopped by the same shield, and
+    /// the player then cannot be hit until the grace runs out.
+    pub fn break_shield(&mut self, power_ups: &PowerUpTuning) -> bool {
+        if !self.end(PowerUpKind::Shield) {
+            return false;
+        }
+        self.invulnerable = power_ups.shield_grace;
+        true
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.
+    }
+
+    /// How fast time passes for the enemies.
+    pub fn enemy_time_scale(&self, power_ups: &PowerUpTuning) -> f32 {
+        if self.is_active(PowerUpKind::SlowMo) {
+            power_ups.slow_mo_time_scale
+        } else {
+            1.
+        }
+    }
+
+    /// How much faster the player moves.
+    pub fn player_speed_multiplier(&self, power_ups: &PowerUpTuning) -> f32 {
+        if self.is_active(PowerUpKind::SpeedBoost) {
+            power_ups.speed_boost_multiplier
+        } else {
+            1.
+        }
+    }
+}
+
+pub fn power_up_collider(tuning: &Tuning) -> Collider {
+    Collider::circle(
+        tuning.power_ups.size / 2.,
+        CollisionLayers::new(POWER_UP_LAYER, PLAYER_LAYER),
+    )
+}
+
+pub fn reset_active_power_ups(mut active_power_ups: ResMut<ActivePowerUps>) {
+    *active_power_ups = ActivePowerUps::default();
+}
+
+pub fn despawn_power_ups(mut commands: Commands, power_up_query: Query<Entity, With<PowerUp>>) {
+    power_up_query.for_each(|power_up| {
+        commands.entity(power_up).despawn();
+    })
+}
+
+pub fn spawn_power_ups_over_time(
+    mut commands: Commands,
+    star_spawn_timer: Res<StarSpawnTimer>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    if !star_spawn_timer.timer.finished() || game_rng.gen::<f32>() >= tuning.power_ups.chance {
+        return;
+    }
+
+    let kind = PowerUpKind::ALL[game_rng.gen_range(0..PowerUpKind::ALL.len())];
+    let position = arena.random_position(tuning.power_ups.size, &mut *game_rng);
+    commands.spawn((
+        PositionBundle::new(position),
+        power_up_collider(&tuning),
+        ConfinedToArena::Clamp,
+        PowerUp {
+            kind,
+            remaining: tuning.power_ups.lifetime,
+        },
+    ));
+}
+
+pub fn tick_power_up_lifetimes(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    mut power_up_query: Query<(Entity, &mut PowerUp)>,
+) {
+    for (entity, mut power_up) in power_up_query.iter_mut() {
+        power_up.remaining -= tick_seconds(&fixed_time);
+        if power_up.remaining <= 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn tick_active_power_ups(
+    fixed_time: Res<FixedTime>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    mut power_up_expired_event_writer: EventWriter<PowerUpExpired>,
+) {
+    if active_power_ups.remaining.is_empty() && !active_power_ups.is_invulnerable() {
+        return;
+    }
+
+    let seconds = tick_seconds(&fixed_time);
+    if active_power_ups.is_invulnerable() {
+        active_power_ups.invulnerable = (active_power_ups.invulnerable - seconds).max(0.);
+    }
+    active_power_ups.remaining.retain(|&kind, remaining| {
+        *remaining -= seconds;
+        if *remaining > 0. {
+            return true;
+        }
+        power_up_expired_event_writer.send(PowerUpExpired { kind });
+        false
+    });
+}
+
+/// Enemies run on slowed down time while slow-mo is on. New enemies are
+/// spawned with the scale already set.
+pub fn apply_slow_mo_to_enemies(
+    tuning: Res<Tuning>,
+    active_power_ups: Res<ActivePowerUps>,
+    mut enemy_query: Query<&mut TimeScale, With<Enemy>>,
+) {
+    let scale = active_power_ups.enemy_time_scale(&tuning.power_ups);
+    for mut time_scale in enemy_query.iter_mut() {
+        if time_scale.0 != scale {
+            time_scale.0 = scale;
+        }
+    }
+}
+
+/// Stars within reach of the magnet fly to the player.
+pub fn pull_stars_to_player(
+    fixed_time: Res<FixedTime>,
+    tuning: Res<Tuning>,
+    active_power_ups: Res<ActivePowerUps>,
+    player_query: Query<&Position, (With<Player>, Without<Star>)>,
+    mut star_query: Query<&mut Position, With<Star>>,
+) {
+    if !active_power_ups.is_active(PowerUpKind::Magnet) {
+        return;
+    }
+    let Ok(player_position) = player_query.get_single() else {
+        return;
+    };
+
+    let step = tuning.power_ups.magnet_speed * tick_seconds(&fixed_time);
+    for mut position in star_query.iter_mut() {
+        let offset = player_position.0 - position.0;
+        if offset.length() <= tuning.power_ups.magnet_radius {
+            position.0 += offset.clamp_length_max(step);
+        }
+    }
+}
+
+pub fn player_hit_power_up(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<CollisionStarted>,
+    tuning: Res<Tuning>,
+    player_query: Query<(), With<Player>>,
+    power_up_query: Query<(&PowerUp, &Position)>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    mut power_up_collected_event_writer: EventWriter<PowerUpCollected>,
+) {
+    for collision in collision_event_reader.iter() {
+        if let Some((_, power_up_entity)) = collision.between(
+            |entity| player_query.contains(entity),
+            |entity| power_up_query.contains(entity),
+        ) {
+            if let Ok((power_up, position)) = power_up_query.get(power_up_entity) {
+                active_power_ups.start(power_up.kind, tuning.power_ups.timing(power_up.kind));
+                power_up_collected_event_writer.send(PowerUpCollected {
+                    kind: power_up.kind,
+                    position: position.0,
+                });
+            }
+            commands.entity(power_up_entity).despawn();
+        }
+    }
+}
+
+pub fn apply_tuning_to_power_up_colliders(
+    tuning: Res<Tuning>,
+    mut power_up_query: Query<&mut Collider, With<PowerUp>>,
+) {
+    for mut collider in power_up_query.iter_mut() {
+        *collider = power_up_collider(&tuning);
+    }
+}
@@ -0,0 +1,414 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::events::RunEnded;
+use crate::AppState;
+
+use super::arena::Arena;
+use super::input::{ControlScheme, InputSystemSet, PlayerInput, TogglePause};
+use super::mode::{Difficulty, GameMode};
+use super::rng::{reset_game_rng, GameRng, GameSeed};
+use super::tick::TickCount;
+use super::tuning::Tuning;
+use super::waves::Waves;
+use super::SimulationState;
+
+/// Bumped whenever the replay format or the gameplay changes in a way that
+/// old replays would not play back the same.
+pub const REPLAY_VERSION: u32 = 7;
+
+/// Everything needed to play a game again tick for tick: the settings it ran
+/// with and the player input of every tick.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub tick_period: Duration,
+    pub arena: Arena,
+    pub tuning: Tuning,
+    pub mode: GameMode,
+    /// Only played in the waves mode.
+    pub waves: Waves,
+    pub difficulty: Difficulty,
+    /// How the player steered. Movement is recorded the same either way.
+    pub control_scheme: ControlScheme,
+    /// Movement input of every tick, with runs of equal input merged.
+    pub movement: Vec<MovementRun>,
+    pub pauses: Vec<PauseChange>,
+    /// Score the game ended with.
+    pub score: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MovementRun {
+    pub ticks: u64,
+    pub movement: [f32; 2],
+}
+
+/// The player paused or resumed the game after the given number of ticks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PauseChange {
+    pub tick: u64,
+    pub paused: bool,
+}
+
+#[derive(Deserialize)]
+struct ReplayVersion {
+    version: u32,
+}
+
+impl Replay {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        seed: u64,
+        tick_period: Duration,
+        arena: Arena,
+        tuning: Tuning,
+        mode: GameMode,
+        waves: Waves,
+        difficulty: Difficulty,
+        control_scheme: ControlScheme,
+    ) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            tick_period,
+            arena,
+            tuning,
+            mode,
+            waves,
+            difficulty,
+            control_scheme,
+            movement: Vec::new(),
+            pauses: Vec::new(),
+            score: 0,
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ReplayError> {
+        let text = std::fs::read_to_string(path).map_err(ReplayError::Io)?;
+        // Check the version on its own first, a replay from another version
+        // may not even parse.
+        let ReplayVersion { version } = ron::from_str(&text).map_err(ReplayError::Parse)?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        ron::from_str(&text).map_err(ReplayError::Parse)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReplayError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(ReplayError::Serialize)?;
+        std::fs::write(path, text).map_err(ReplayError::Io)
+    }
+
+    pub fn push_movement(&mut self, movement: Vec2) {
+        let movement = movement.to_array();
+        match self.movement.last_mut() {
+            Some(run) if run.movement == movement => run.ticks += 1,
+            _ => self.movement.push(MovementRun { ticks: 1, movement }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "could not access the replay: {error}"),
+            ReplayError::Parse(error) => write!(f, "could not parse the replay: {error}"),
+            ReplayError::Serialize(error) => write!(f, "could not write the replay: {error}"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "the replay is version {version}, this game only plays version {REPLAY_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Records every game into a replay file, written when the game is over.
+pub struct ReplayRecordingPlugin {
+    pub path: PathBuf,
+}
+
+impl Plugin for ReplayRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplayRecorder {
+            path: self.path.clone(),
+            replay: None,
+        })
+        .add_systems(
+            OnEnter(AppState::Game),
+            (start_recording.after(reset_game_rng),),
+        )
+        .add_systems(
+            FixedUpdate,
+            record_movement
+                .after(InputSystemSet)
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(SimulationState::Running)),
+        )
+        .add_systems(
+            Update,
+            (
+                record_pauses
+                    .after(InputSystemSet)
+                    .run_if(in_state(AppState::Game)),
+                save_recording,
+            ),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    pub path: PathBuf,
+    pub replay: Option<Replay>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_recording(
+    mut replay_recorder: ResMut<ReplayRecorder>,
+    game_rng: Res<GameRng>,
+    fixed_time: Res<FixedTime>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    game_mode: Res<GameMode>,
+    waves: Res<Waves>,
+    difficulty: Res<Difficulty>,
+    control_scheme: Res<ControlScheme>,
+) {
+    replay_recorder.replay = Some(Replay::new(
+        game_rng.seed,
+        fixed_time.period,
+        *arena,
+        tuning.clone(),
+        *game_mode,
+        waves.clone(),
+        *difficulty,
+        *control_scheme,
+    ));
+}
+
+pub fn record_movement(
+    mut replay_recorder: ResMut<ReplayRecorder>,
+    player_input: Res<PlayerInput>,
+) {
+    if let Some(replay) = &mut replay_recorder.replay {
+        replay.push_movement(player_input.movement);
+    }
+}
+
+pub fn record_pauses(
+    mut replay_recorder: ResMut<ReplayRecorder>,
+    mut toggle_pause_event_reader: EventReader<TogglePause>,
+    simulation_state: Res<State<SimulationState>>,
+    tick_count: Res<TickCount>,
+) {
+    if toggle_pause_event_reader.iter().last().is_some() {
+        if let Some(replay) = &mut replay_recorder.replay {
+            replay.pauses.push(PauseChange {
+                tick: tick_count.ticks,
+                paused: simulation_state.get() == &SimulationState::Running,
+            });
+        }
+    }
+}
+
+pub fn save_recording(
+    mut replay_recorder: ResMut<ReplayRecorder>,
+    mut run_ended_event_reader: EventReader<RunEnded>,
+) {
+    if let Some(run_ended) = run_ended_event_reader.iter().last() {
+        let path = replay_recorder.path.clone();
+        if let Some(mut replay) = replay_recorder.replay.take() {
+            replay.score = run_ended.score;
+            match replay.write(&path) {
+                Ok(()) => println!("Saved replay to {}", path.display()),
+                Err(error) => eprintln!("{error}"),
+            }
+        }
+    }
+}
+
+/// Plays a replay back: runs every game with the replay's settings and feeds
+/// its input in place of the player's.
+pub struct ReplayPlaybackPlugin {
+    pub replay: Replay,
+}
+
+impl Plugin for ReplayPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        let replay = self.replay.clone();
+
+        app.insert_resource(GameSeed {
+            seed: Some(replay.seed),
+        })
+        .insert_resource(FixedTime::new(replay.tick_period))
+        .insert_resource(replay.arena)
+        .insert_resource(replay.tuning.clone())
+        .insert_resource(replay.mode)
+        .insert_resource(replay.waves.clone())
+        .insert_resource(replay.difficulty)
+        .insert_resource(replay.control_scheme)
+        .insert_resource(ReplayPlayback::new(replay))
+        .add_systems(OnEnter(AppState::Game), (rewind_playback,))
+        .add_systems(
+            FixedUpdate,
+            play_back_movement
+                .in_set(InputSystemSet)
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(SimulationState::Running)),
+        )
+        .add_systems(
+            Update,
+            (
+                play_back_pauses
+                    .in_set(InputSystemSet)
+                    .run_if(in_state(AppState::Game)),
+                compare_playback_result,
+            ),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    movement_run: usize,
+    ticks_into_run: u64,
+    next_pause: usize,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            movement_run: 0,
+            ticks_into_run: 0,
+            next_pause: 0,
+        }
+    }
+
+    pub fn rewind(&mut self) {
+        self.movement_run = 0;
+        self.ticks_into_run = 0;
+        self.next_pause = 0;
+    }
+
+    /// Movement of the next tick, none once the recording runs out.
+    pub fn next_movement(&mut self) -> Vec2 {
+        while let Some(run) = self.replay.movement.get(self.movement_run) {
+            if self.ticks_into_run < run.ticks {
+                self.ticks_into_run += 1;
+                return Vec2::from_array(run.movement);
+            }
+            self.movement_run += 1;
+            self.ticks_into_run = 0;
+        }
+        Vec2::ZERO
+    }
+}
+
+pub fn rewind_playback(mut replay_playback: ResMut<ReplayPlayback>) {
+    replay_playback.rewind();
+}
+
+pub fn play_back_movement(
+    mut replay_playback: ResMut<ReplayPlayback>,
+    mut player_input: ResMut<PlayerInput>,
+) {
+    player_input.movement = replay_playback.next_movement();
+}
+
+/// Pausing does not change what happens in a game, only when, so each change
+/// is applied on the first frame its tick has been reached.
+pub fn play_back_pauses(
+    mut replay_playback: ResMut<ReplayPlayback>,
+    simulation_state: Res<State<SimulationState>>,
+    tick_count: Res<TickCount>,
+    mut toggle_pause_event_writer: EventWriter<TogglePause>,
+) {
+    let next_pause = replay_playback.next_pause;
+    if let Some(pause_change) = replay_playback.replay.pauses.get(next_pause).copied() {
+        if pause_change.tick <= tick_count.ticks {
+            let paused = simulation_state.get() == &SimulationState::Paused;
+            if pause_change.paused != paused {
+                toggle_pause_event_writer.send(TogglePause);
+            }
+            replay_playback.next_pause += 1;
+        }
+    }
+}
+
+pub fn compare_playback_result(
+    replay_playback: Res<ReplayPlayback>,
+    mut run_ended_event_reader: EventReader<RunEnded>,
+) {
+    if let Some(run_ended) = run_ended_event_reader.iter().last() {
+        let recorded_score = replay_playback.replay.score;
+        if run_ended.score == recorded_score {
+            println!("Replay matched the recorded score of {}", recorded_score);
+        } else {
+            println!(
+                "Replay diverged: scored {}, the recording scored {}",
+                run_ended.score, recorded_score
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::input::read_movement_input;
+    use crate::headless::tests::{headless_app, run_until_game_over};
+
+    use super::*;
+
+    /// Moves the player in wide circles, so it picks up some stars before an
+    /// enemy gets it.
+    fn circle_around(tick_count: Res<TickCount>, mut player_input: ResMut<PlayerInput>) {
+        player_input.movement = Vec2::from_angle(tick_count.ticks as f32 * 0.02);
+    }
+
+    #[test]
+    fn played_back_run_scores_the_same() {
+        let path = std::env::temp_dir().join(format!(
+            "bevy_ball_game_replay_test_{}.ron",
+            std::process::id()
+        ));
+
+        let mut recording_app = headless_app(1);
+        recording_app
+            .add_plugins(ReplayRecordingPlugin { path: path.clone() })
+            .add_systems(
+                FixedUpdate,
+                circle_around
+                    .in_set(InputSystemSet)
+                    .after(read_movement_input),
+            );
+        let recorded_score = run_until_game_over(&mut recording_app).unwrap();
+        let replay = Replay::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(recorded_score > 0);
+        assert_eq!(replay.score, recorded_score);
+
+        // A different seed, the replay's own has to take over.
+        let mut playback_app = headless_app(2);
+        playback_app.add_plugins(ReplayPlaybackPlugin { replay });
+        assert_eq!(run_until_game_over(&mut playback_app), Some(recorded_score));
+    }
+}
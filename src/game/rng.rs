@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{random, RngCore, SeedableRng};
+
+/// Seed for the next games, set at launch. A fresh seed is picked for every
+/// game when it is not set.
+#[derive(Resource, Default)]
+pub struct GameSeed {
+    pub seed: Option<u64>,
+}
+
+/// The random number generator every gameplay system draws from, so that a
+/// game can be reproduced from its seed.
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(0)
+    }
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+pub fn reset_game_rng(game_seed: Res<GameSeed>, mut game_rng: ResMut<GameRng>) {
+    let seed = game_seed.seed.unwrap_or_else(random);
+    *game_rng = GameRng::from_seed(seed);
+    println!("Game seed: {}", seed);
+}
@@ -0,0 +1,234 @@
+use bevy::prelude::*;
+
+use super::arena::Arena;
+use super::enemy::{enemy_size, Enemy, EnemyKind, EnemyScale};
+use super::enemy_kinds::{DashPhase, Dasher};
+use super::player::Player;
+use super::power_ups::{ActivePowerUps, PowerUp, PowerUpKind};
+use super::star::Star;
+use super::tuning::Tuning;
+
+/// How many times a second a telegraphing dasher flashes.
+pub const DASHER_FLASH_RATE: f32 = 8.;
+
+/// Gives the gameplay entities their sprites. Left out when running without a
+/// renderer.
+pub struct GameSpritesPlugin;
+
+impl Plugin for GameSpritesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ClearColor(Color::BLACK)).add_systems(
+            Update,
+            (
+                fit_arena_backdrop,
+                add_player_sprites,
+                add_enemy_sprites,
+                add_star_sprites,
+                add_power_up_sprites,
+                flash_telegraphing_dashers,
+                tint_shielded_player.run_if(resource_changed::<ActivePowerUps>()),
+                (
+                    resize_player_sprites,
+                    resize_enemy_sprites,
+                    resize_star_sprites,
+                    resize_power_up_sprites,
+                )
+                    .run_if(resource_changed::<Tuning>()),
+            ),
+        );
+    }
+}
+
+/// Marks out the arena, so the letterboxing around it is visible when the
+/// window does not have the arena's aspect ratio.
+#[derive(Component)]
+pub struct ArenaBackdrop;
+
+pub fn fit_arena_backdrop(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    mut backdrop_query: Query<(&mut Sprite, &mut Transform), With<ArenaBackdrop>>,
+) {
+    if !arena.is_changed() {
+        return;
+    }
+
+    let center = arena.center();
+    let transform = Transform::from_xyz(center.x, center.y, -1.);
+
+    if let Ok((mut sprite, mut backdrop_transform)) = backdrop_query.get_single_mut() {
+        sprite.custom_size = Some(arena.size());
+        *backdrop_transform = transform;
+    } else {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.4, 0.4, 0.4),
+                    custom_size: Some(arena.size()),
+                    ..Default::default()
+                },
+                transform,
+                ..Default::default()
+            },
+            ArenaBackdrop,
+        ));
+    }
+}
+
+pub fn add_player_sprites(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    asset_server: Res<AssetServer>,
+    tuning: Res<Tuning>,
+) {
+    player_query.for_each(|player| {
+        commands.entity(player).insert(sprite_components(
+            asset_server.load("sprites/ball_blue_large.png"),
+            tuning.player_size,
+        ));
+    })
+}
+
+pub fn add_enemy_sprites(
+    mut commands: Commands,
+    enemy_query: Query<(Entity, &EnemyKind, &EnemyScale), Added<Enemy>>,
+    asset_server: Res<AssetServer>,
+    tuning: Res<Tuning>,
+) {
+    enemy_query.for_each(|(enemy, kind, scale)| {
+        let definition = tuning.enemy_kind(*kind);
+        let (mut sprite, texture, visibility) = sprite_components(
+            asset_server.load("sprites/ball_red_large.png"),
+            enemy_size(&tuning, definition, *scale),
+        );
+        sprite.color = enemy_tint(definition.tint);
+        commands.entity(enemy).insert((sprite, texture, visibility));
+    })
+}
+
+fn enemy_tint([red, green, blue]: [f32; 3]) -> Color {
+    Color::rgb(red, green, blue)
+}
+
+/// Dashers about to lunge flash white.
+pub fn flash_telegraphing_dashers(
+    time: Res<Time>,
+    tuning: Res<Tuning>,
+    mut dasher_query: Query<(&EnemyKind, &Dasher, &mut Sprite)>,
+) {
+    let flash = (time.elapsed_seconds() * DASHER_FLASH_RATE).fract() < 0.5;
+    for (kind, dasher, mut sprite) in dasher_query.iter_mut() {
+        sprite.color = if dasher.phase == DashPhase::Telegraph && flash {
+            Color::WHITE
+        } else {
+            enemy_tint(tuning.enemy_kind(*kind).tint)
+        };
+    }
+}
+
+pub fn add_star_sprites(
+    mut commands: Commands,
+    star_query: Query<Entity, Added<Star>>,
+    asset_server: Res<AssetServer>,
+    tuning: Res<Tuning>,
+) {
+    star_query.for_each(|star| {
+        commands.entity(star).insert(sprite_components(
+            asset_server.load("sprites/star.png"),
+            tuning.star_size,
+        ));
+    })
+}
+
+pub fn add_power_up_sprites(
+    mut commands: Commands,
+    power_up_query: Query<(Entity, &PowerUp), Added<PowerUp>>,
+    asset_server: Res<AssetServer>,
+    tuning: Res<Tuning>,
+) {
+    power_up_query.for_each(|(entity, power_up)| {
+        let (mut sprite, texture, visibility) = sprite_components(
+            asset_server.load("sprites/ball_blue_large.png"),
+            tuning.power_ups.size,
+        );
+        sprite.color = power_up_tint(power_up.kind);
+        commands
+            .entity(entity)
+            .insert((sprite, texture, visibility));
+    })
+}
+
+fn power_up_tint(kind: PowerUpKind) -> Color {
+    match kind {
+        PowerUpKind::Shield => Color::rgb(0.5, 1., 1.),
+        PowerUpKind::SpeedBoost => Color::rgb(1., 1., 0.3),
+        PowerUpKind::SlowMo => Color::rgb(0.8, 0.5, 1.),
+        PowerUpKind::Magnet => Color::rgb(0.5, 1., 0.5),
+    }
+}
+
+/// The player takes the shield's color while it is up, and fades out while
+/// the grace after it breaks runs.
+pub fn tint_shielded_player(
+    active_power_ups: Res<ActivePowerUps>,
+    mut player_query: Query<&mut Sprite, With<Player>>,
+) {
+    for mut sprite in player_query.iter_mut() {
+        sprite.color = if active_power_ups.is_active(PowerUpKind::Shield) {
+            power_up_tint(PowerUpKind::Shield)
+        } else if active_power_ups.is_invulnerable() {
+            Color::WHITE.with_a(0.5)
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+pub fn resize_player_sprites(
+    tuning: Res<Tuning>,
+    mut player_query: Query<&mut Sprite, With<Player>>,
+) {
+    for mut sprite in player_query.iter_mut() {
+        sprite.custom_size = Some(Vec2::splat(tuning.player_size));
+    }
+}
+
+pub fn resize_enemy_sprites(
+    tuning: Res<Tuning>,
+    mut enemy_query: Query<(&EnemyKind, &EnemyScale, &mut Sprite), With<Enemy>>,
+) {
+    for (kind, scale, mut sprite) in enemy_query.iter_mut() {
+        let definition = tuning.enemy_kind(*kind);
+        sprite.custom_size = Some(Vec2::splat(enemy_size(&tuning, definition, *scale)));
+        sprite.color = enemy_tint(definition.tint);
+    }
+}
+
+pub fn resize_star_sprites(tuning: Res<Tuning>, mut star_query: Query<&mut Sprite, With<Star>>) {
+    for mut sprite in star_query.iter_mut() {
+        sprite.custom_size = Some(Vec2::splat(tuning.star_size));
+    }
+}
+
+pub fn resize_power_up_sprites(
+    tuning: Res<Tuning>,
+    mut power_up_query: Query<&mut Sprite, With<PowerUp>>,
+) {
+    for mut sprite in power_up_query.iter_mut() {
+        sprite.custom_size = Some(Vec2::splat(tuning.power_ups.size));
+    }
+}
+
+fn sprite_components(
+    texture: Handle<Image>,
+    size: f32,
+) -> (Sprite, Handle<Image>, VisibilityBundle) {
+    (
+        Sprite {
+            custom_size: Some(Vec2::splat(size)),
+            ..Default::default()
+        },
+        texture,
+        VisibilityBundle::default(),
+    )
+}
@@ -1,27 +1,44 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
-use rand::random;
 
 use crate::AppState;
 
+use super::arena::Arena;
+use super::collision::{Collider, CollisionLayers, PLAYER_LAYER, STAR_LAYER};
+use super::confinement::ConfinedToArena;
+use super::difficulty::{DifficultyDirector, DifficultySystemSet};
+use super::enemy::{spawn_enemies, spawn_enemies_over_time};
+use super::rng::GameRng;
+use super::tick::PositionBundle;
+use super::tuning::Tuning;
 use super::SimulationState;
 
-pub const STAR_SIZE: f32 = 30.;
-pub const NUMBER_OF_STARS: usize = 10;
-pub const STAR_SPAWN_DURATION: f32 = 1.;
-
 pub struct StarPlugin;
 
 impl Plugin for StarPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<StarSpawnTimer>()
-            .add_systems(OnEnter(AppState::Game), (spawn_stars,))
+            .add_systems(
+                OnEnter(AppState::Game),
+                (reset_star_spawn_timer, spawn_stars.after(spawn_enemies)),
+            )
             .add_systems(OnExit(AppState::Game), (despawn_stars,))
             .add_systems(
-                Update,
-                (tick_star_spawn_timer, spawn_stars_over_time)
+                FixedUpdate,
+                (
+                    apply_difficulty_to_star_spawn_timer.after(DifficultySystemSet),
+                    tick_star_spawn_timer.after(apply_difficulty_to_star_spawn_timer),
+                    spawn_stars_over_time
+                        .after(tick_star_spawn_timer)
+                        .after(spawn_enemies_over_time),
+                )
                     .run_if(in_state(AppState::Game))
                     .run_if(in_state(SimulationState::Running)),
+            )
+            .add_systems(
+                Update,
+                apply_tuning_to_star_colliders.run_if(resource_changed::<Tuning>()),
             );
     }
 }
@@ -37,74 +54,88 @@ pub struct StarSpawnTimer {
 impl Default for StarSpawnTimer {
     fn default() -> Self {
         Self {
-            timer: Timer::from_seconds(STAR_SPAWN_DURATION, TimerMode::Repeating),
+            timer: Timer::from_seconds(Tuning::default().star_spawn_duration, TimerMode::Repeating),
         }
     }
 }
 
+pub fn star_collider(tuning: &Tuning) -> Collider {
+    Collider::circle(
+        tuning.star_size / 2.,
+        CollisionLayers::new(STAR_LAYER, PLAYER_LAYER),
+    )
+}
+
 pub fn spawn_stars(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let window = window_query.get_single().unwrap();
-
-    let half_star_size = STAR_SIZE / 2.;
-    let x_min = 0. + half_star_size;
-    let x_max = window.width() - half_star_size;
-    let y_min = 0. + half_star_size;
-    let y_max = window.height() - half_star_size;
-
-    for _ in 0..NUMBER_OF_STARS {
-        let position_x = x_min + random::<f32>() * (x_max - x_min);
-        let position_y = y_min + random::<f32>() * (y_max - y_min);
+    for _ in 0..tuning.number_of_stars {
+        let position = arena.random_position(tuning.star_size, &mut *game_rng);
 
         commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_xyz(position_x, position_y, 0.),
-                texture: asset_server.load("sprites/star.png"),
-                ..Default::default()
-            },
+            PositionBundle::new(position),
+            star_collider(&tuning),
+            ConfinedToArena::Clamp,
             Star {},
         ));
     }
 }
 
+pub fn reset_star_spawn_timer(mut star_spawn_timer: ResMut<StarSpawnTimer>) {
+    star_spawn_timer.timer.reset();
+}
+
 pub fn despawn_stars(mut commands: Commands, star_query: Query<Entity, With<Star>>) {
     star_query.for_each(|star| {
         commands.entity(star).despawn();
     })
 }
 
-pub fn tick_star_spawn_timer(mut star_spawn_timer: ResMut<StarSpawnTimer>, time: Res<Time>) {
-    star_spawn_timer.timer.tick(time.delta());
+pub fn tick_star_spawn_timer(
+    mut star_spawn_timer: ResMut<StarSpawnTimer>,
+    fixed_time: Res<FixedTime>,
+) {
+    star_spawn_timer.timer.tick(fixed_time.period);
 }
 
 pub fn spawn_stars_over_time(
     mut commands: Commands,
     star_spawn_timer: Res<StarSpawnTimer>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     if star_spawn_timer.timer.finished() {
-        let window = window_query.get_single().unwrap();
-
-        let half_star_size = STAR_SIZE / 2.;
-        let x_min = 0. + half_star_size;
-        let x_max = window.width() - half_star_size;
-        let y_min = 0. + half_star_size;
-        let y_max = window.height() - half_star_size;
-
-        let position_x = x_min + random::<f32>() * (x_max - x_min);
-        let position_y = y_min + random::<f32>() * (y_max - y_min);
+        let position = arena.random_position(tuning.star_size, &mut *game_rng);
 
         commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_xyz(position_x, position_y, 0.),
-                texture: asset_server.load("sprites/star.png"),
-                ..Default::default()
-            },
+            PositionBundle::new(position),
+            star_collider(&tuning),
+            ConfinedToArena::Clamp,
             Star {},
         ));
     }
 }
+
+pub fn apply_difficulty_to_star_spawn_timer(
+    mut star_spawn_timer: ResMut<StarSpawnTimer>,
+    difficulty_director: Res<DifficultyDirector>,
+) {
+    if difficulty_director.is_changed() {
+        star_spawn_timer.timer.set_duration(Duration::from_secs_f32(
+            difficulty_director.star_spawn_duration,
+        ));
+    }
+}
+
+pub fn apply_tuning_to_star_colliders(
+    tuning: Res<Tuning>,
+    mut star_query: Query<&mut Collider, With<Star>>,
+) {
+    for mut collider in star_query.iter_mut() {
+        *collider = star_collider(&tuning);
+    }
+}
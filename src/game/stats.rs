@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use crate::events::{EnemyBounced, EnemySpawned, RunEnded, RunStarted, StarCollected, WaveCleared};
+
+use super::enemy::Enemy;
+use super::high_scores::HighScores;
+use super::mode::{Difficulty, GameMode};
+use super::replay::ReplayPlayback;
+use super::rng::GameRng;
+use super::tick::{tick_seconds, TickCount};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .init_resource::<LastRun>()
+            .add_systems(Update, (count_run_events, record_last_run).chain());
+    }
+}
+
+/// Counts of what happened in the current game, kept from the gameplay
+/// events.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct RunStats {
+    pub stars_collected: u32,
+    pub enemies_spawned: u32,
+    /// Bounces off walls and off other enemies, two enemies bouncing off
+    /// each other counting once.
+    pub enemy_bounces: u32,
+    pub waves_cleared: u32,
+}
+
+/// Summary of the last game that ended, for the game over screen.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct LastRun {
+    pub score: u32,
+    /// How long the game lasted, in seconds.
+    pub duration: f32,
+    /// Enemies in the arena when the player was hit.
+    pub enemies: usize,
+    pub stats: RunStats,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    /// Where the score ranks in its high score table, starting at zero, if
+    /// it made it in. Replays never do, they are not new games.
+    pub high_score_rank: Option<usize>,
+}
+
+pub fn count_run_events(
+    mut run_started_event_reader: EventReader<RunStarted>,
+    mut star_collected_event_reader: EventReader<StarCollected>,
+    mut enemy_spawned_event_reader: EventReader<EnemySpawned>,
+    mut enemy_bounced_event_reader: EventReader<EnemyBounced>,
+    mut wave_cleared_event_reader: EventReader<WaveCleared>,
+    mut run_stats: ResMut<RunStats>,
+) {
+    if run_started_event_reader.iter().last().is_some() {
+        *run_stats = RunStats::default();
+    }
+    run_stats.stars_collected += star_collected_event_reader.iter().count() as u32;
+    run_stats.enemies_spawned += enemy_spawned_event_reader.iter().count() as u32;
+    run_stats.enemy_bounces += enemy_bounced_event_reader
+        .iter()
+        .filter(|bounce| bounce.other.is_none_or(|other| bounce.entity < other))
+        .count() as u32;
+    run_stats.waves_cleared += wave_cleared_event_reader.iter().count() as u32;
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn record_last_run(
+    mut run_ended_event_reader: EventReader<RunEnded>,
+    mut last_run: ResMut<LastRun>,
+    run_stats: Res<RunStats>,
+    tick_count: Res<TickCount>,
+    fixed_time: Res<FixedTime>,
+    game_rng: Res<GameRng>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    high_scores: Res<HighScores>,
+    enemy_query: Query<(), With<Enemy>>,
+    replay_playback: Option<Res<ReplayPlayback>>,
+) {
+    for event in run_ended_event_reader.iter() {
+        *last_run = LastRun {
+            score: event.score,
+            duration: tick_count.ticks as f32 * tick_seconds(&fixed_time),
+            enemies: enemy_query.iter().count(),
+            stats: *run_stats,
+            seed: game_rng.seed,
+            mode: *game_mode,
+            difficulty: *difficulty,
+            high_score_rank: if replay_playback.is_some() {
+                None
+            } else {
+                high_scores.rank(*game_mode, *difficulty, event.score)
+            },
+        };
+    }
+}
@@ -0,0 +1,614 @@
+use bevy::prelude::*;
+use bevy_ball_game::spatial_hash::SpatialHash;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+use super::arena::Arena;
+use super::collision::Collider;
+use super::player::{MovementSystemSet, Player};
+use super::rng::GameRng;
+use super::tick::{
+    scaled_tick_seconds, store_previous_positions, tick_seconds, Position, PreviousPosition,
+    TimeScale, Velocity,
+};
+use super::SimulationState;
+
+/// Turns the velocity of every entity with a [`Steering`] component toward
+/// what its behaviors ask for, before anything moves.
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_set(
+            FixedUpdate,
+            SteeringSystemSet
+                // Targets without a velocity are followed by how far they
+                // moved last tick, which takes their previous position.
+                .before(store_previous_positions)
+                .before(MovementSystemSet),
+        )
+        .add_systems(
+            FixedUpdate,
+            steer
+                .in_set(SteeringSystemSet)
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(SimulationState::Running)),
+        );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SteeringSystemSet;
+
+/// Steers an entity by the weighted sum of its behaviors, within a top speed,
+/// a top acceleration and a turn rate. The arena walls still stop or bounce
+/// it the way its [`ConfinedToArena`](super::confinement::ConfinedToArena)
+/// says, [`SteeringBehavior::AvoidWalls`] keeps it from running into them.
+#[derive(Component, Debug, Clone)]
+pub struct Steering {
+    pub behaviors: Vec<WeightedBehavior>,
+    pub max_speed: f32,
+    /// Most the velocity can change by in a second. Infinite to change at
+    /// once.
+    pub max_force: f32,
+    /// Radians a second the heading can turn by. Infinite for no limit.
+    pub turn_rate: f32,
+    /// Where the wander target is on its circle, in radians from the heading.
+    pub wander_angle: f32,
+}
+
+impl Steering {
+    pub fn new(behaviors: Vec<WeightedBehavior>, max_speed: f32) -> Self {
+        Self {
+            behaviors,
+            max_speed,
+            max_force: f32::INFINITY,
+            turn_rate: f32::INFINITY,
+            wander_angle: 0.,
+        }
+    }
+
+    pub fn with_max_force(mut self, max_force: f32) -> Self {
+        self.max_force = max_force;
+        self
+    }
+
+    pub fn with_turn_rate(mut self, turn_rate: f32) -> Self {
+        self.turn_rate = turn_rate;
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WeightedBehavior {
+    pub behavior: SteeringBehavior,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+impl WeightedBehavior {
+    pub fn new(behavior: SteeringBehavior, weight: f32) -> Self {
+        Self { behavior, weight }
+    }
+}
+
+fn default_weight() -> f32 {
+    1.
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SteeringTarget {
+    Player,
+    /// A point, as fractions of the arena from its bottom left corner.
+    At(f32, f32),
+    /// Any entity with a position. Only available from code.
+    #[serde(skip)]
+    Entity(Entity),
+}
+
+/// One thing a steered entity wants to do. Distances are in units, angles in
+/// radians and durations in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SteeringBehavior {
+    /// Heads straight for the target at top speed.
+    Seek(SteeringTarget),
+    /// Runs straight away from the target while it is closer than `distance`.
+    Flee {
+        target: SteeringTarget,
+        distance: f32,
+    },
+    /// Heads for the target and slows down once it is closer than
+    /// `slowing_distance`, to stop on it.
+    Arrive {
+        target: SteeringTarget,
+        slowing_distance: f32,
+    },
+    /// Heads for where the target will be if it keeps going, looking at most
+    /// `prediction` seconds ahead.
+    Pursue {
+        target: SteeringTarget,
+        prediction: f32,
+    },
+    /// Drifts about at random, heading for a point on a circle of `radius`
+    /// that is `distance` ahead and moves along the circle by up to `jitter`
+    /// radians a second.
+    Wander {
+        radius: f32,
+        distance: f32,
+        jitter: f32,
+    },
+    /// Moves away from other steered entities closer than `distance`, harder
+    /// the closer they are.
+    Separation { distance: f32 },
+    /// Moves along with the other steered entities closer than `distance`,
+    /// keeping apart from them, matching their heading and heading for their
+    /// middle with the given weights.
+    Flock {
+        distance: f32,
+        separation: f32,
+        alignment: f32,
+        cohesion: f32,
+    },
+    /// Turns away from the walls closer than `distance`, harder the closer
+    /// they are.
+    AvoidWalls { distance: f32 },
+}
+
+impl SteeringBehavior {
+    /// How far away other steered entities are taken into account.
+    fn neighbor_distance(&self) -> f32 {
+        match *self {
+            SteeringBehavior::Separation { distance }
+            | SteeringBehavior::Flock { distance, .. } => distance,
+            _ => 0.,
+        }
+    }
+}
+
+/// Adds a problem for each behavior, weight or limit that cannot steer
+/// anything, naming them after `name`.
+pub fn validate_steering(
+    name: &str,
+    behaviors: &[WeightedBehavior],
+    max_force: f32,
+    turn_rate: f32,
+    problems: &mut Vec<String>,
+) {
+    for (field, value) in [("max_force", max_force), ("turn_rate", turn_rate)] {
+        if value.is_nan() || value <= 0. {
+            problems.push(format!(
+                "`{name}.{field}` must be more than zero, got {value}"
+            ));
+        }
+    }
+    for (index, WeightedBehavior { behavior, weight }) in behaviors.iter().enumerate() {
+        if !weight.is_finite() {
+            problems.push(format!(
+                "`{name}.behaviors[{index}].weight` must be a number, got {weight}"
+            ));
+        }
+        // Distances get divided by, they cannot be zero.
+        let (distances, others): (&[f32], &[f32]) = match behavior {
+            SteeringBehavior::Seek(_) => (&[], &[]),
+            SteeringBehavior::Flee { distance, .. }
+            | SteeringBehavior::Separation { distance }
+            | SteeringBehavior::AvoidWalls { distance } => (&[*distance], &[]),
+            SteeringBehavior::Arrive {
+                slowing_distance, ..
+            } => (&[*slowing_distance], &[]),
+            SteeringBehavior::Pursue { prediction, .. } => (&[], &[*prediction]),
+            SteeringBehavior::Wander {
+                radius,
+                distance,
+                jitter,
+            } => (&[], &[*radius, *distance, *jitter]),
+            SteeringBehavior::Flock {
+                distance,
+                separation,
+                alignment,
+                cohesion,
+            } => (&[*distance], &[*separation, *alignment, *cohesion]),
+        };
+        if distances
+            .iter()
+            .any(|value| !value.is_finite() || *value <= 0.)
+            || others.iter().any(|value| !value.is_finite() || *value < 0.)
+        {
+            problems.push(format!(
+                "`{name}.behaviors[{index}]` distances must be more than zero and the other values must not be negative"
+            ));
+        }
+    }
+}
+
+/// Position and velocity of an entity at the start of the tick.
+#[derive(Debug, Clone, Copy)]
+struct Motion {
+    position: Vec2,
+    velocity: Vec2,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn steer(
+    arena: Res<Arena>,
+    fixed_time: Res<FixedTime>,
+    mut game_rng: ResMut<GameRng>,
+    player_query: Query<Entity, With<Player>>,
+    target_query: Query<
+        (&Position, Option<&PreviousPosition>, Option<&Velocity>),
+        Without<Steering>,
+    >,
+    mut steering_query: Query<(
+        Entity,
+        &mut Steering,
+        &Position,
+        &mut Velocity,
+        Option<&Collider>,
+        Option<&TimeScale>,
+    )>,
+) {
+    let seconds = tick_seconds(&fixed_time);
+
+    // Everything steers from where the others were at the start of the tick,
+    // whichever order they come in.
+    let steered: Vec<(Entity, Motion)> = steering_query
+        .iter()
+        .map(|(entity, _, position, velocity, ..)| {
+            (
+                entity,
+                Motion {
+                    position: position.0,
+                    velocity: velocity.0,
+                },
+            )
+        })
+        .collect();
+    let neighbor_distance = steering_query
+        .iter()
+        .flat_map(|(_, steering, ..)| steering.behaviors.iter())
+        .map(|weighted| weighted.behavior.neighbor_distance())
+        .fold(0., f32::max);
+    let mut neighbors = SpatialHash::new(neighbor_distance.max(1.));
+    if neighbor_distance > 0. {
+        for (index, (_, motion)) in steered.iter().enumerate() {
+            neighbors.insert(index, motion.position, Vec2::ZERO);
+        }
+    }
+
+    let player = player_query.get_single().ok();
+    let locate = |target: SteeringTarget| -> Option<Motion> {
+        let entity = match target {
+            SteeringTarget::Player => player?,
+            SteeringTarget::At(x, y) => {
+                return Some(Motion {
+                    position: arena.size() * Vec2::new(x, y),
+                    velocity: Vec2::ZERO,
+                })
+            }
+            SteeringTarget::Entity(entity) => entity,
+        };
+        if let Ok((position, previous_position, velocity)) = target_query.get(entity) {
+            let velocity = match (velocity, previous_position) {
+                (Some(velocity), _) => velocity.0,
+                (None, Some(previous_position)) => (position.0 - previous_position.0) / seconds,
+                (None, None) => Vec2::ZERO,
+            };
+            return Some(Motion {
+                position: position.0,
+                velocity,
+            });
+        }
+        steered
+            .iter()
+            .find(|(steered_entity, _)| *steered_entity == entity)
+            .map(|(_, motion)| *motion)
+    };
+
+    for (entity, mut steering, position, mut velocity, collider, time_scale) in
+        steering_query.iter_mut()
+    {
+        // Targets moved in real time, the entity turns and wanders in its own.
+        let seconds = scaled_tick_seconds(&fixed_time, time_scale);
+        let max_speed = steering.max_speed;
+        let motion = Motion {
+            position: position.0,
+            velocity: velocity.0,
+        };
+        let nearby = |distance: f32| {
+            neighbors
+                .query(position.0, Vec2::splat(distance))
+                .into_iter()
+                .map(|index| steered[index])
+                .filter(|(other, other_motion)| {
+                    *other != entity && other_motion.position.distance(position.0) <= distance
+                })
+                .map(|(_, other_motion)| other_motion)
+                .collect::<Vec<_>>()
+        };
+
+        let mut force = Vec2::ZERO;
+        for index in 0..steering.behaviors.len() {
+            let WeightedBehavior { behavior, weight } = steering.behaviors[index];
+            let behavior_force = match behavior {
+                SteeringBehavior::Seek(target) => locate(target).map_or(Vec2::ZERO, |target| {
+                    seek(motion, target.position, max_speed)
+                }),
+                SteeringBehavior::Flee { target, distance } => locate(target)
+                    .map_or(Vec2::ZERO, |target| {
+                        flee(motion, target.position, distance, max_speed)
+                    }),
+                SteeringBehavior::Arrive {
+                    target,
+                    slowing_distance,
+                } => locate(target).map_or(Vec2::ZERO, |target| {
+                    arrive(motion, target.position, slowing_distance, max_speed)
+                }),
+                SteeringBehavior::Pursue { target, prediction } => locate(target)
+                    .map_or(Vec2::ZERO, |target| {
+                        pursue(motion, target, prediction, max_speed)
+                    }),
+                SteeringBehavior::Wander {
+                    radius,
+                    distance,
+                    jitter,
+                } => {
+                    steering.wander_angle += (game_rng.gen::<f32>() * 2. - 1.) * jitter * seconds;
+                    wander(motion, steering.wander_angle, radius, distance, max_speed)
+                }
+                SteeringBehavior::Separation { distance } => {
+                    separation(motion, &nearby(distance), distance, max_speed)
+                }
+                SteeringBehavior::Flock {
+                    distance,
+                    separation: separation_weight,
+                    alignment,
+                    cohesion,
+                } => {
+                    let flock = nearby(distance);
+                    if flock.is_empty() {
+                        Vec2::ZERO
+                    } else {
+                        let count = flock.len() as f32;
+                        let heading: Vec2 =
+                            flock.iter().map(|neighbor| neighbor.velocity).sum::<Vec2>() / count;
+                        let middle: Vec2 =
+                            flock.iter().map(|neighbor| neighbor.position).sum::<Vec2>() / count;
+                        separation(motion, &flock, distance, max_speed) * separation_weight
+                            + (heading.clamp_length_max(max_speed) - velocity.0) * alignment
+                            + seek(motion, middle, max_speed) * cohesion
+                    }
+                }
+                SteeringBehavior::AvoidWalls { distance } => {
+                    let half_extents =
+                        collider.map_or(Vec2::ZERO, |collider| collider.shape.half_extents());
+                    let bounds = arena.inset_bounds(half_extents);
+                    avoid_walls(position.0, bounds, distance, max_speed)
+                }
+            };
+            force += behavior_force * weight;
+        }
+
+        velocity.0 = apply_steering_force(&steering, velocity.0, force, seconds);
+    }
+}
+
+/// Full speed toward `point`.
+fn seek(motion: Motion, point: Vec2, max_speed: f32) -> Vec2 {
+    (point - motion.position)
+        .try_normalize()
+        .map_or(Vec2::ZERO, |direction| {
+            direction * max_speed - motion.velocity
+        })
+}
+
+/// Full speed away from `point` while it is closer than `distance`.
+fn flee(motion: Motion, point: Vec2, distance: f32, max_speed: f32) -> Vec2 {
+    if point.distance(motion.position) >= distance {
+        return Vec2::ZERO;
+    }
+    (motion.position - point)
+        .try_normalize()
+        .map_or(Vec2::ZERO, |direction| {
+            direction * max_speed - motion.velocity
+        })
+}
+
+/// Toward `point`, slowing down within `slowing_distance` of it so it comes
+/// to rest there.
+fn arrive(motion: Motion, point: Vec2, slowing_distance: f32, max_speed: f32) -> Vec2 {
+    ((point - motion.position) / slowing_distance * max_speed).clamp_length_max(max_speed)
+        - motion.velocity
+}
+
+/// Toward where `target` will be, looking at most `prediction` seconds ahead
+/// and less the closer it is.
+fn pursue(motion: Motion, target: Motion, prediction: f32, max_speed: f32) -> Vec2 {
+    let distance = target.position.distance(motion.position);
+    let look_ahead = if max_speed > 0. {
+        (distance / max_speed).min(prediction)
+    } else {
+        prediction
+    };
+    seek(
+        motion,
+        target.position + target.velocity * look_ahead,
+        max_speed,
+    )
+}
+
+/// Toward a point `wander_angle` around a circle of `radius` held `distance`
+/// ahead of the heading.
+fn wander(motion: Motion, wander_angle: f32, radius: f32, distance: f32, max_speed: f32) -> Vec2 {
+    let heading = motion.velocity.try_normalize().unwrap_or(Vec2::X);
+    seek(
+        motion,
+        motion.position
+            + heading * distance
+            + Vec2::from_angle(wander_angle).rotate(heading) * radius,
+        max_speed,
+    )
+}
+
+/// Away from the `neighbors`, harder the closer they are within `distance`.
+fn separation(motion: Motion, neighbors: &[Motion], distance: f32, max_speed: f32) -> Vec2 {
+    let push: Vec2 = neighbors
+        .iter()
+        .map(|neighbor| {
+            let offset = motion.position - neighbor.position;
+            offset.normalize_or_zero() * (1. - offset.length() / distance)
+        })
+        .sum();
+    push.clamp_length_max(1.) * max_speed
+}
+
+/// Away from the sides of `bounds` closer than `distance`, harder the closer
+/// they are.
+fn avoid_walls(position: Vec2, bounds: Rect, distance: f32, max_speed: f32) -> Vec2 {
+    let closeness = |gap: f32| (1. - gap / distance).max(0.);
+    let push = Vec2::new(
+        closeness(position.x - bounds.min.x) - closeness(bounds.max.x - position.x),
+        closeness(position.y - bounds.min.y) - closeness(bounds.max.y - position.y),
+    );
+    push.clamp_length_max(1.) * max_speed
+}
+
+/// `velocity` after `force` acted on it for `seconds`, within the limits of
+/// the steering.
+fn apply_steering_force(steering: &Steering, velocity: Vec2, force: Vec2, seconds: f32) -> Vec2 {
+    let change = if steering.max_force.is_finite() {
+        force.clamp_length_max(steering.max_force * seconds)
+    } else {
+        force
+    };
+    let steered_velocity = (velocity + change).clamp_length_max(steering.max_speed);
+    limit_turn(velocity, steered_velocity, steering.turn_rate * seconds)
+}
+
+/// `to`, turned back toward the heading of `from` so the two are at most
+/// `max_turn` radians apart.
+fn limit_turn(from: Vec2, to: Vec2, max_turn: f32) -> Vec2 {
+    let (Some(heading), Some(direction)) = (from.try_normalize(), to.try_normalize()) else {
+        return to;
+    };
+    let turn = heading.angle_between(direction).clamp(-max_turn, max_turn);
+    Vec2::from_angle(turn).rotate(heading) * to.length()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECONDS: f32 = 1. / 60.;
+
+    fn at_rest(position: Vec2) -> Motion {
+        Motion {
+            position,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    /// `motion` a tick later under `force`, moved the way [`steer`] and the
+    /// movement systems do.
+    fn step(steering: &Steering, motion: Motion, force: Vec2) -> Motion {
+        let velocity = apply_steering_force(steering, motion.velocity, force, SECONDS);
+        Motion {
+            position: motion.position + velocity * SECONDS,
+            velocity,
+        }
+    }
+
+    #[test]
+    fn limit_turn_stops_at_the_limit_either_way() {
+        for (to, turn) in [(Vec2::Y * 50., 0.1), (Vec2::NEG_Y * 50., -0.1)] {
+            let turned = limit_turn(Vec2::X * 100., to, 0.1);
+
+            assert!((Vec2::X.angle_between(turned) - turn).abs() < 1e-5);
+            assert!((turned.length() - 50.).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn limit_turn_leaves_smaller_turns_alone() {
+        let to = Vec2::new(100., 5.);
+
+        assert!(limit_turn(Vec2::X * 100., to, 0.5).distance(to) < 1e-3);
+    }
+
+    #[test]
+    fn limit_turn_lets_a_stop_or_a_start_through() {
+        assert_eq!(limit_turn(Vec2::X, Vec2::ZERO, 0.1), Vec2::ZERO);
+        assert_eq!(limit_turn(Vec2::ZERO, Vec2::Y, 0.1), Vec2::Y);
+    }
+
+    #[test]
+    fn turn_rate_is_respected() {
+        let steering = Steering::new(Vec::new(), 200.).with_turn_rate(2.);
+        let mut motion = Motion {
+            position: Vec2::ZERO,
+            velocity: Vec2::X * 200.,
+        };
+        let behind = Vec2::new(-500., 10.);
+
+        for _ in 0..120 {
+            let velocity = motion.velocity;
+            motion = step(&steering, motion, seek(motion, behind, 200.));
+
+            assert!(velocity.angle_between(motion.velocity).abs() <= 2. * SECONDS + 1e-5);
+        }
+    }
+
+    #[test]
+    fn seek_heads_straight_for_the_point_at_full_speed() {
+        let steering = Steering::new(Vec::new(), 200.);
+        let mut motion = at_rest(Vec2::ZERO);
+        motion = step(&steering, motion, seek(motion, Vec2::new(30., 40.), 200.));
+
+        assert!(motion.velocity.distance(Vec2::new(120., 160.)) < 1e-3);
+    }
+
+    #[test]
+    fn arrive_comes_to_rest_at_the_target() {
+        let steering = Steering::new(Vec::new(), 200.).with_max_force(800.);
+        let target = Vec2::new(300., 100.);
+        let mut motion = at_rest(Vec2::ZERO);
+
+        for _ in 0..600 {
+            motion = step(&steering, motion, arrive(motion, target, 100., 200.));
+        }
+
+        assert!(motion.position.distance(target) < 1.);
+        assert!(motion.velocity.length() < 1.);
+    }
+
+    #[test]
+    fn flee_only_runs_from_close_points() {
+        let motion = at_rest(Vec2::ZERO);
+
+        assert!(flee(motion, Vec2::new(50., 0.), 100., 200.).x < 0.);
+        assert_eq!(flee(motion, Vec2::new(150., 0.), 100., 200.), Vec2::ZERO);
+    }
+
+    #[test]
+    fn separation_pushes_away_from_neighbors() {
+        let force = separation(at_rest(Vec2::ZERO), &[at_rest(Vec2::X * 20.)], 100., 200.);
+
+        assert!(force.x < 0.);
+        assert_eq!(force.y, 0.);
+    }
+
+    #[test]
+    fn avoid_walls_pushes_away_from_close_walls_only() {
+        let bounds = Rect::new(0., 0., 1000., 1000.);
+
+        let force = avoid_walls(Vec2::new(10., 500.), bounds, 100., 200.);
+        assert!(force.x > 0.);
+        assert_eq!(force.y, 0.);
+        assert_eq!(
+            avoid_walls(Vec2::new(500., 500.), bounds, 100., 200.),
+            Vec2::ZERO
+        );
+    }
+}
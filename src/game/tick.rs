@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+
+use super::player::MovementSystemSet;
+use super::SimulationState;
+
+pub const DEFAULT_TICK_RATE: f32 = 60.;
+
+/// Steps the gameplay at a fixed rate and smooths the rendered transforms
+/// between ticks.
+pub struct TickPlugin;
+
+impl Plugin for TickPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FixedTime::new_from_secs(1. / DEFAULT_TICK_RATE))
+            .init_resource::<TickCount>()
+            .add_systems(OnEnter(AppState::Game), (reset_tick_count,))
+            .add_systems(
+                FixedUpdate,
+                (
+                    count_ticks.before(MovementSystemSet),
+                    store_previous_positions.before(MovementSystemSet),
+                    apply_velocities.in_set(MovementSystemSet),
+                )
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            )
+            .add_systems(
+                Update,
+                interpolate_transforms
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            );
+    }
+}
+
+/// Number of ticks the current game has run for.
+#[derive(Resource, Debug, Default)]
+pub struct TickCount {
+    pub ticks: u64,
+}
+
+/// Gameplay position, only changed on fixed ticks.
+#[derive(Component, Debug, Default, Clone, Copy, Deref, DerefMut)]
+pub struct Position(pub Vec2);
+
+/// Position at the start of the current tick, used for interpolation.
+#[derive(Component, Debug, Default, Clone, Copy, Deref, DerefMut)]
+pub struct PreviousPosition(pub Vec2);
+
+/// Units per second, applied to the position every tick.
+#[derive(Component, Debug, Default, Clone, Copy, Deref, DerefMut)]
+pub struct Velocity(pub Vec2);
+
+/// How fast time passes for an entity, 1 being normal. Scales its velocity
+/// and the timers of its behaviors.
+#[derive(Component, Debug, Clone, Copy, Deref, DerefMut)]
+pub struct TimeScale(pub f32);
+
+#[derive(Bundle, Default)]
+pub struct PositionBundle {
+    pub position: Position,
+    pub previous_position: PreviousPosition,
+    pub transform: TransformBundle,
+}
+
+impl PositionBundle {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position: Position(position),
+            previous_position: PreviousPosition(position),
+            transform: TransformBundle::from_transform(Transform::from_xyz(
+                position.x, position.y, 0.,
+            )),
+        }
+    }
+}
+
+/// Seconds of gameplay that pass in one tick.
+pub fn tick_seconds(fixed_time: &FixedTime) -> f32 {
+    fixed_time.period.as_secs_f32()
+}
+
+/// Seconds that pass in one tick for an entity, after its [`TimeScale`].
+pub fn scaled_tick_seconds(fixed_time: &FixedTime, time_scale: Option<&TimeScale>) -> f32 {
+    tick_seconds(fixed_time) * time_scale.map_or(1., |time_scale| time_scale.0)
+}
+
+pub fn reset_tick_count(mut tick_count: ResMut<TickCount>) {
+    tick_count.ticks = 0;
+}
+
+pub fn count_ticks(mut tick_count: ResMut<TickCount>) {
+    tick_count.ticks += 1;
+}
+
+pub fn store_previous_positions(mut query: Query<(&Position, &mut PreviousPosition)>) {
+    for (position, mut previous_position) in query.iter_mut() {
+        previous_position.0 = position.0;
+    }
+}
+
+pub fn apply_velocities(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut Position, &Velocity, Option<&TimeScale>)>,
+) {
+    for (mut position, velocity, time_scale) in query.iter_mut() {
+        position.0 += velocity.0 * scaled_tick_seconds(&fixed_time, time_scale);
+    }
+}
+
+pub fn interpolate_transforms(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut Transform, &Position, &PreviousPosition)>,
+) {
+    let alpha = (fixed_time.accumulated().as_secs_f32() / tick_seconds(&fixed_time)).min(1.);
+
+    for (mut transform, position, previous_position) in query.iter_mut() {
+        let translation = previous_position.lerp(position.0, alpha);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
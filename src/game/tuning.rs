@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use super::difficulty::DifficultyCurves;
+use super::enemy::EnemyKind;
+use super::enemy_kinds::{default_enemy_kinds, EnemyKindDefinition, DEFAULT_ENEMY_KIND};
+use super::power_ups::PowerUpTuning;
+use super::replay::ReplayPlayback;
+
+pub const TUNING_PATH: &str = "game.tuning.ron";
+
+/// Gameplay values the designers tweak, loaded from `assets/game.tuning.ron`.
+/// Falls back to the built in defaults when the file is not available.
+#[derive(Resource, Serialize, Deserialize, TypeUuid, TypePath, Debug, Clone, PartialEq)]
+#[uuid = "5a9e41f1-8e0f-4bf1-a3d1-5a3b0c2b1d67"]
+#[serde(default, deny_unknown_fields)]
+pub struct Tuning {
+    pub player_speed: f32,
+    pub player_size: f32,
+    pub enemy_speed: f32,
+    pub enemy_size: f32,
+    pub number_of_enemies: usize,
+    pub enemy_spawn_duration: f32,
+    pub enemies_collide: bool,
+    /// How each kind of enemy looks and moves. Kinds left out are plain
+    /// bouncers.
+    pub enemy_kinds: BTreeMap<EnemyKind, EnemyKindDefinition>,
+    /// Kinds to pick from for each enemy of the endless mode. A kind listed
+    /// more than once comes up that much more often.
+    pub endless_kinds: Vec<EnemyKind>,
+    pub number_of_stars: usize,
+    pub star_size: f32,
+    pub star_spawn_duration: f32,
+    /// Pickups that show up with the stars now and then.
+    pub power_ups: PowerUpTuning,
+    /// How the enemy and star values above change as a game goes on.
+    pub difficulty_curves: DifficultyCurves,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            player_speed: 500.,
+            player_size: 64.,
+            enemy_speed: 200.,
+            enemy_size: 64.,
+            number_of_enemies: 4,
+            enemy_spawn_duration: 5.,
+            enemies_collide: true,
+            enemy_kinds: default_enemy_kinds().into_iter().collect(),
+            endless_kinds: vec![EnemyKind::Bouncer],
+            number_of_stars: 10,
+            star_size: 30.,
+            star_spawn_duration: 1.,
+            power_ups: PowerUpTuning::default(),
+            difficulty_curves: DifficultyCurves::default(),
+        }
+    }
+}
+
+impl Tuning {
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, TuningError> {
+        let tuning: Tuning = ron::de::from_bytes(bytes).map_err(TuningError::Parse)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    pub fn enemy_kind(&self, kind: EnemyKind) -> &EnemyKindDefinition {
+        self.enemy_kinds.get(&kind).unwrap_or(&DEFAULT_ENEMY_KIND)
+    }
+
+    pub fn validate(&self) -> Result<(), TuningError> {
+        let mut problems = Vec::new();
+
+        for (name, value) in [
+            ("player_speed", self.player_speed),
+            ("enemy_speed", self.enemy_speed),
+        ] {
+            if !value.is_finite() || value < 0. {
+                problems.push(format!("`{name}` must be zero or more, got {value}"));
+            }
+        }
+        for (name, value) in [
+            ("player_size", self.player_size),
+            ("enemy_size", self.enemy_size),
+            ("star_size", self.star_size),
+            ("enemy_spawn_duration", self.enemy_spawn_duration),
+            ("star_spawn_duration", self.star_spawn_duration),
+        ] {
+            if !value.is_finite() || value <= 0. {
+                problems.push(format!("`{name}` must be more than zero, got {value}"));
+            }
+        }
+        for (kind, definition) in &self.enemy_kinds {
+            definition.validate(&format!("{kind:?}"), &mut problems);
+        }
+        if self.endless_kinds.is_empty() {
+            problems.push("`endless_kinds` must have at least one kind".to_string());
+        }
+        self.power_ups.validate(&mut problems);
+        self.difficulty_curves.validate(&mut problems);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(TuningError::Invalid(problems))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Parse(ron::error::SpannedError),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::Parse(error) => write!(f, "could not parse {TUNING_PATH}: {error}"),
+            TuningError::Invalid(problems) => {
+                write!(f, "invalid values in {TUNING_PATH}:")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
+
+#[derive(Default)]
+pub struct TuningLoader;
+
+impl AssetLoader for TuningLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let tuning = Tuning::from_ron(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(tuning));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning.ron"]
+    }
+}
+
+/// Loads the tuning file through the asset server and applies every change
+/// made to it while the game runs. Needs the asset server, so it is left out
+/// of headless runs, which read the file once instead. Stands aside while a
+/// replay is played back, the replay brings its own tuning.
+pub struct TuningAssetPlugin;
+
+impl Plugin for TuningAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Tuning>()
+            .init_asset_loader::<TuningLoader>()
+            .add_systems(Startup, (load_tuning,))
+            .add_systems(
+                Update,
+                apply_loaded_tuning.run_if(not(resource_exists::<ReplayPlayback>())),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct TuningHandle {
+    pub handle: Handle<Tuning>,
+}
+
+pub fn load_tuning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(TuningHandle {
+        handle: asset_server.load(TUNING_PATH),
+    });
+}
+
+pub fn apply_loaded_tuning(
+    mut tuning_event_reader: EventReader<AssetEvent<Tuning>>,
+    tuning_assets: Res<Assets<Tuning>>,
+    mut tuning: ResMut<Tuning>,
+) {
+    for event in tuning_event_reader.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(loaded_tuning) = tuning_assets.get(handle) {
+                if *tuning != *loaded_tuning {
+                    *tuning = loaded_tuning.clone();
+                    println!("Applied {}", TUNING_PATH);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the tuning file straight from disk, for runs without an asset
+/// server. Keeps the defaults when the file is missing or invalid.
+pub fn read_tuning_file(mut tuning: ResMut<Tuning>) {
+    let path = format!("assets/{}", TUNING_PATH);
+    match std::fs::read(&path) {
+        Ok(bytes) => match Tuning::from_ron(&bytes) {
+            Ok(loaded_tuning) => *tuning = loaded_tuning,
+            Err(error) => eprintln!("{error}, using the default tuning"),
+        },
+        Err(error) => eprintln!("could not read {path}: {error}, using the default tuning"),
+    }
+}
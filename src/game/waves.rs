@@ -0,0 +1,566 @@
+use std::fmt;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EnemySpawned, StarCollected, WaveCleared, WaveStarted};
+use crate::AppState;
+
+use super::arena::Arena;
+use super::difficulty::{DifficultyDirector, DifficultySystemSet};
+use super::enemy::{enemy_size, spawn_enemy, EnemyKind, EnemyScale};
+use super::mode::GameMode;
+use super::player::player_hit_star;
+use super::power_ups::ActivePowerUps;
+use super::replay::ReplayPlayback;
+use super::rng::{reset_game_rng, GameRng};
+use super::tick::tick_seconds;
+use super::tuning::Tuning;
+use super::SimulationState;
+
+pub const WAVES_PATH: &str = "game.waves.ron";
+
+/// Spawns the enemies of the waves game mode, one wave after another.
+pub struct WavesPlugin;
+
+impl Plugin for WavesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Waves>()
+            .init_resource::<WaveState>()
+            .add_systems(
+                OnEnter(AppState::Game),
+                (reset_waves.after(reset_game_rng),),
+            )
+            .add_systems(
+                FixedUpdate,
+                run_waves
+                    .after(DifficultySystemSet)
+                    .after(player_hit_star)
+                    .run_if(resource_equals(GameMode::Waves))
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            );
+    }
+}
+
+/// The authored waves, loaded from `assets/game.waves.ron`, and how to make up
+/// more once they run out. Falls back to the built in waves when the file is
+/// not available.
+#[derive(Resource, Serialize, Deserialize, TypeUuid, TypePath, Debug, Clone, PartialEq)]
+#[uuid = "8d3b7a20-4f61-4c2e-b5a9-6e1f0d9c7b42"]
+#[serde(default, deny_unknown_fields)]
+pub struct Waves {
+    pub waves: Vec<Wave>,
+    /// Seconds between one wave clearing and the next starting, and before
+    /// the first.
+    pub break_duration: f32,
+    pub endless: EndlessWaves,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Wave {
+    pub groups: Vec<SpawnGroup>,
+    pub clear: ClearCondition,
+}
+
+/// Enemies of one kind spawned from the same place, one after another.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnGroup {
+    #[serde(default)]
+    pub kind: EnemyKind,
+    pub count: u32,
+    #[serde(default)]
+    pub spawn: SpawnPoint,
+    /// Seconds from the start of the wave to the first enemy.
+    #[serde(default)]
+    pub delay: f32,
+    /// Seconds between enemies.
+    #[serde(default)]
+    pub interval: f32,
+}
+
+/// Where the enemies of a group appear.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum SpawnPoint {
+    /// Anywhere in the arena.
+    #[default]
+    Random,
+    /// A point given as fractions of the arena's width and height, so it
+    /// works whatever the arena's size.
+    At(f32, f32),
+    /// Anywhere along an edge.
+    Edge(ArenaEdge),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    /// Any of the four, picked for each enemy.
+    Any,
+}
+
+/// What the player has to do for a wave to clear, once all of its enemies
+/// have spawned.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ClearCondition {
+    /// Stay alive for this many seconds from the start of the wave.
+    Survive(f32),
+    /// Collect this many stars during the wave.
+    CollectStars(u32),
+}
+
+/// Waves made up once the authored ones run out, each bigger than the last.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EndlessWaves {
+    /// Kinds to pick from for each group.
+    pub kinds: Vec<EnemyKind>,
+    /// Enemies in the first generated wave.
+    pub count: u32,
+    /// More enemies in each generated wave than the one before.
+    pub count_growth: u32,
+    /// Enemies are split into groups of at most this many.
+    pub group_size: u32,
+    pub interval: f32,
+    /// Seconds each generated wave has to be survived.
+    pub survive: f32,
+}
+
+impl Default for Waves {
+    fn default() -> Self {
+        Self {
+            waves: vec![
+                Wave {
+                    groups: vec![SpawnGroup {
+                        kind: EnemyKind::Bouncer,
+                        count: 3,
+                        spawn: SpawnPoint::Edge(ArenaEdge::Top),
+                        delay: 0.,
+                        interval: 0.5,
+                    }],
+                    clear: ClearCondition::Survive(10.),
+                },
+                Wave {
+                    groups: vec![
+                        SpawnGroup {
+                            kind: EnemyKind::Bouncer,
+                            count: 2,
+                            spawn: SpawnPoint::Edge(ArenaEdge::Left),
+                            delay: 0.,
+                            interval: 0.5,
+                        },
+                        SpawnGroup {
+                            kind: EnemyKind::Bouncer,
+                            count: 2,
+                            spawn: SpawnPoint::Edge(ArenaEdge::Right),
+                            delay: 2.,
+                            interval: 0.5,
+                        },
+                    ],
+                    clear: ClearCondition::CollectStars(5),
+                },
+                Wave {
+                    groups: vec![
+                        SpawnGroup {
+                            kind: EnemyKind::Chaser,
+                            count: 2,
+                            spawn: SpawnPoint::Edge(ArenaEdge::Any),
+                            delay: 0.,
+                            interval: 1.,
+                        },
+                        SpawnGroup {
+                            kind: EnemyKind::Zigzagger,
+                            count: 2,
+                            spawn: SpawnPoint::Edge(ArenaEdge::Top),
+                            delay: 2.,
+                            interval: 0.5,
+                        },
+                        SpawnGroup {
+                            kind: EnemyKind::Orbiter,
+                            count: 1,
+                            spawn: SpawnPoint::Random,
+                            delay: 4.,
+                            interval: 0.,
+                        },
+                    ],
+                    clear: ClearCondition::Survive(20.),
+                },
+                Wave {
+                    groups: vec![
+                        SpawnGroup {
+                            kind: EnemyKind::Dasher,
+                            count: 2,
+                            spawn: SpawnPoint::Edge(ArenaEdge::Left),
+                            delay: 0.,
+                            interval: 1.,
+                        },
+                        SpawnGroup {
+                            kind: EnemyKind::Splitter,
+                            count: 1,
+                            spawn: SpawnPoint::At(0.5, 1.),
+                            delay: 3.,
+                            interval: 0.,
+                        },
+                    ],
+                    clear: ClearCondition::CollectStars(8),
+                },
+            ],
+            break_duration: 3.,
+            endless: EndlessWaves {
+                kinds: vec![
+                    EnemyKind::Bouncer,
+                    EnemyKind::Chaser,
+                    EnemyKind::Zigzagger,
+                    EnemyKind::Orbiter,
+                    EnemyKind::Dasher,
+                    EnemyKind::Splitter,
+                ],
+                count: 3,
+                count_growth: 1,
+                group_size: 3,
+                interval: 0.5,
+                survive: 15.,
+            },
+        }
+    }
+}
+
+impl Waves {
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, WavesError> {
+        let waves: Waves = ron::de::from_bytes(bytes).map_err(WavesError::Parse)?;
+        waves.validate()?;
+        Ok(waves)
+    }
+
+    pub fn validate(&self) -> Result<(), WavesError> {
+        let mut problems = Vec::new();
+
+        for (index, wave) in self.waves.iter().enumerate() {
+            let number = index + 1;
+            if wave.groups.is_empty() {
+                problems.push(format!("wave {number} has no enemies"));
+            }
+            for group in &wave.groups {
+                if !group.delay.is_finite() || group.delay < 0. {
+                    problems.push(format!("wave {number} has a negative `delay`"));
+                }
+                if !group.interval.is_finite() || group.interval < 0. {
+                    problems.push(format!("wave {number} has a negative `interval`"));
+                }
+                if let SpawnPoint::At(x, y) = group.spawn {
+                    if !(0. ..=1.).contains(&x) || !(0. ..=1.).contains(&y) {
+                        problems.push(format!(
+                            "wave {number} spawns at ({x}, {y}), both must be between 0 and 1"
+                        ));
+                    }
+                }
+            }
+            if let ClearCondition::Survive(seconds) = wave.clear {
+                if !seconds.is_finite() || seconds < 0. {
+                    problems.push(format!("wave {number} has a negative `Survive` time"));
+                }
+            }
+        }
+        if !self.break_duration.is_finite() || self.break_duration < 0. {
+            problems.push(format!(
+                "`break_duration` must be zero or more, got {}",
+                self.break_duration
+            ));
+        }
+        if self.endless.kinds.is_empty() {
+            problems.push("`endless.kinds` must have at least one kind".to_string());
+        }
+        if self.endless.group_size == 0 {
+            problems.push("`endless.group_size` must be more than zero".to_string());
+        }
+        for (name, value) in [
+            ("endless.interval", self.endless.interval),
+            ("endless.survive", self.endless.survive),
+        ] {
+            if !value.is_finite() || value < 0. {
+                problems.push(format!("`{name}` must be zero or more, got {value}"));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(WavesError::Invalid(problems))
+        }
+    }
+
+    /// The wave with the given number, counting from one: an authored one,
+    /// or a generated one past the last.
+    pub fn wave(&self, number: u32, rng: &mut impl Rng) -> Wave {
+        if let Some(wave) = self.waves.get(number as usize - 1) {
+            return wave.clone();
+        }
+
+        let endless = &self.endless;
+        let generated = number - self.waves.len() as u32 - 1;
+        let mut remaining = endless.count + endless.count_growth * generated;
+        let mut groups = Vec::new();
+        while remaining > 0 {
+            let count = remaining.min(endless.group_size);
+            groups.push(SpawnGroup {
+                kind: endless.kinds[rng.gen_range(0..endless.kinds.len())],
+                count,
+                spawn: SpawnPoint::Edge(ArenaEdge::Any),
+                delay: groups.len() as f32 * endless.interval * endless.group_size as f32,
+                interval: endless.interval,
+            });
+            remaining -= count;
+        }
+        Wave {
+            groups,
+            clear: ClearCondition::Survive(endless.survive),
+        }
+    }
+}
+
+impl SpawnPoint {
+    /// Where the next enemy of the given size appears, fully inside the
+    /// arena.
+    pub fn position(self, arena: &Arena, size: f32, rng: &mut impl Rng) -> Vec2 {
+        let bounds = arena.bounds(size);
+        // Each edge from its bottom or left end to its top or right end.
+        let edges = [
+            (Vec2::new(bounds.min.x, bounds.max.y), bounds.max),
+            (bounds.min, Vec2::new(bounds.max.x, bounds.min.y)),
+            (bounds.min, Vec2::new(bounds.min.x, bounds.max.y)),
+            (Vec2::new(bounds.max.x, bounds.min.y), bounds.max),
+        ];
+        let (start, end) = match self {
+            SpawnPoint::Random => return arena.random_position(size, rng),
+            SpawnPoint::At(x, y) => {
+                return bounds.min + Vec2::new(x, y) * bounds.size();
+            }
+            SpawnPoint::Edge(ArenaEdge::Top) => edges[0],
+            SpawnPoint::Edge(ArenaEdge::Bottom) => edges[1],
+            SpawnPoint::Edge(ArenaEdge::Left) => edges[2],
+            SpawnPoint::Edge(ArenaEdge::Right) => edges[3],
+            SpawnPoint::Edge(ArenaEdge::Any) => edges[rng.gen_range(0..edges.len())],
+        };
+        start.lerp(end, rng.gen())
+    }
+}
+
+#[derive(Debug)]
+pub enum WavesError {
+    Parse(ron::error::SpannedError),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for WavesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavesError::Parse(error) => write!(f, "could not parse {WAVES_PATH}: {error}"),
+            WavesError::Invalid(problems) => {
+                write!(f, "invalid values in {WAVES_PATH}:")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for WavesError {}
+
+#[derive(Default)]
+pub struct WavesLoader;
+
+impl AssetLoader for WavesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let waves = Waves::from_ron(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(waves));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+/// Loads the waves file through the asset server and applies every change
+/// made to it, from the next game on. Left out of headless runs, which read
+/// the file once instead. Stands aside while a replay is played back, the
+/// replay brings its own waves.
+pub struct WavesAssetPlugin;
+
+impl Plugin for WavesAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Waves>()
+            .init_asset_loader::<WavesLoader>()
+            .add_systems(Startup, (load_waves,))
+            .add_systems(
+                Update,
+                apply_loaded_waves.run_if(not(resource_exists::<ReplayPlayback>())),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct WavesHandle {
+    pub handle: Handle<Waves>,
+}
+
+pub fn load_waves(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WavesHandle {
+        handle: asset_server.load(WAVES_PATH),
+    });
+}
+
+pub fn apply_loaded_waves(
+    mut waves_event_reader: EventReader<AssetEvent<Waves>>,
+    waves_assets: Res<Assets<Waves>>,
+    mut waves: ResMut<Waves>,
+) {
+    for event in waves_event_reader.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(loaded_waves) = waves_assets.get(handle) {
+                if *waves != *loaded_waves {
+                    *waves = loaded_waves.clone();
+                    println!("Applied {}", WAVES_PATH);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the waves file straight from disk, for runs without an asset
+/// server. Keeps the built in waves when the file is missing or invalid.
+pub fn read_waves_file(mut waves: ResMut<Waves>) {
+    let path = format!("assets/{}", WAVES_PATH);
+    match std::fs::read(&path) {
+        Ok(bytes) => match Waves::from_ron(&bytes) {
+            Ok(loaded_waves) => *waves = loaded_waves,
+            Err(error) => eprintln!("{error}, using the built in waves"),
+        },
+        Err(error) => eprintln!("could not read {path}: {error}, using the built in waves"),
+    }
+}
+
+/// Progress through the waves of the current game.
+#[derive(Resource, Debug, Default)]
+pub struct WaveState {
+    /// Number of the current or last wave, zero before the first.
+    pub number: u32,
+    /// The wave being played, none during the break before the next.
+    pub wave: Option<Wave>,
+    /// Seconds since the current wave started.
+    pub elapsed: f32,
+    /// Enemies spawned so far from each group of the current wave.
+    pub spawned: Vec<u32>,
+    /// Stars collected during the current wave.
+    pub stars_collected: u32,
+    /// Seconds left before the next wave starts.
+    pub break_remaining: f32,
+}
+
+pub fn reset_waves(mut wave_state: ResMut<WaveState>, waves: Res<Waves>) {
+    *wave_state = WaveState {
+        break_remaining: waves.break_duration,
+        ..Default::default()
+    };
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_waves(
+    mut commands: Commands,
+    mut wave_state: ResMut<WaveState>,
+    waves: Res<Waves>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    difficulty_director: Res<DifficultyDirector>,
+    active_power_ups: Res<ActivePowerUps>,
+    fixed_time: Res<FixedTime>,
+    mut game_rng: ResMut<GameRng>,
+    mut star_collected_event_reader: EventReader<StarCollected>,
+    mut enemy_spawned_event_writer: EventWriter<EnemySpawned>,
+    mut wave_started_event_writer: EventWriter<WaveStarted>,
+    mut wave_cleared_event_writer: EventWriter<WaveCleared>,
+) {
+    let delta = tick_seconds(&fixed_time);
+    let stars_collected = star_collected_event_reader.iter().count() as u32;
+    let wave_state = &mut *wave_state;
+
+    let Some(wave) = &wave_state.wave else {
+        wave_state.break_remaining -= delta;
+        if wave_state.break_remaining <= 0. {
+            wave_state.number += 1;
+            let wave = waves.wave(wave_state.number, &mut *game_rng);
+            wave_state.spawned = vec![0; wave.groups.len()];
+            wave_state.wave = Some(wave);
+            wave_state.elapsed = 0.;
+            wave_state.stars_collected = 0;
+            wave_started_event_writer.send(WaveStarted {
+                number: wave_state.number,
+            });
+        }
+        return;
+    };
+
+    wave_state.elapsed += delta;
+    wave_state.stars_collected += stars_collected;
+
+    for (group, spawned) in wave.groups.iter().zip(wave_state.spawned.iter_mut()) {
+        let due = if wave_state.elapsed < group.delay {
+            0
+        } else if group.interval > 0. {
+            (((wave_state.elapsed - group.delay) / group.interval) as u32 + 1).min(group.count)
+        } else {
+            group.count
+        };
+        while *spawned < due {
+            let size = enemy_size(&tuning, tuning.enemy_kind(group.kind), EnemyScale(1.));
+            let position = group.spawn.position(&arena, size, &mut *game_rng);
+            let entity = spawn_enemy(
+                &mut commands,
+                group.kind,
+                position,
+                difficulty_director.enemy_speed,
+                1.,
+                &tuning,
+                &active_power_ups,
+                &mut game_rng,
+            );
+            enemy_spawned_event_writer.send(EnemySpawned { entity, position });
+            *spawned += 1;
+        }
+    }
+
+    let all_spawned = wave
+        .groups
+        .iter()
+        .zip(&wave_state.spawned)
+        .all(|(group, spawned)| *spawned >= group.count);
+    let cleared = all_spawned
+        && match wave.clear {
+            ClearCondition::Survive(seconds) => wave_state.elapsed >= seconds,
+            ClearCondition::CollectStars(stars) => wave_state.stars_collected >= stars,
+        };
+    if cleared {
+        wave_cleared_event_writer.send(WaveCleared {
+            number: wave_state.number,
+        });
+        wave_state.wave = None;
+        wave_state.break_remaining = waves.break_duration;
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::{app::AppExit, prelude::*, window::ReceivedCharacter};
+
+use crate::game::actions::{Action, ActionState};
+use crate::game::high_scores::{PlayerName, SubmitHighScore};
+use crate::ui::{ButtonActivated, TextInput};
+use crate::AppState;
+
+use super::{GameOverButton, Quitting, PLAYER_NAME_MAX_LENGTH};
+
+/// Typing edits the name of the new high score, Enter saves it under that
+/// name. An empty name is not saved.
+pub fn type_player_name(
+    mut commands: Commands,
+    mut received_character_event_reader: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut player_name: ResMut<PlayerName>,
+    mut submit_high_score_event_writer: EventWriter<SubmitHighScore>,
+) {
+    for event in received_character_event_reader.iter() {
+        if !event.char.is_control() && player_name.name.chars().count() < PLAYER_NAME_MAX_LENGTH {
+            player_name.name.push(event.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        player_name.name.pop();
+    }
+
+    let confirmed = keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter]);
+    if confirmed && !player_name.name.trim().is_empty() {
+        player_name.name = player_name.name.trim().to_string();
+        submit_high_score_event_writer.send(SubmitHighScore);
+        commands.remove_resource::<TextInput>();
+    }
+}
+
+/// Quit enters the pending high score before exiting. Restart and Main Menu
+/// leave the game over screen, which enters it on the way out.
+pub fn handle_game_over_buttons(
+    mut commands: Commands,
+    mut button_activated_event_reader: EventReader<ButtonActivated>,
+    button_query: Query<&GameOverButton>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut submit_high_score_event_writer: EventWriter<SubmitHighScore>,
+) {
+    for event in button_activated_event_reader.iter() {
+        match button_query.get(event.entity) {
+            Ok(GameOverButton::Restart) => next_app_state.set(AppState::Game),
+            Ok(GameOverButton::MainMenu) => next_app_state.set(AppState::MainMenu),
+            Ok(GameOverButton::Quit) => {
+                submit_high_score_event_writer.send(SubmitHighScore);
+                commands.insert_resource(Quitting);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Exits once the pending high score is entered, it is saved the same frame.
+pub fn quit_after_high_score(mut app_exit_event_writer: EventWriter<AppExit>) {
+    app_exit_event_writer.send(AppExit);
+}
+
+/// Back leaves the game over screen for the main menu.
+pub fn go_to_main_menu(
+    action_state: Res<ActionState>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    if action_state.just_pressed(Action::Back) {
+        next_app_state.set(AppState::MainMenu);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::RunEnded;
+    use crate::game::high_scores::{
+        HighScoreEntry, HighScores, HighScoresFile, HighScoresFilePlugin, HighScoresPlugin,
+        PendingHighScore,
+    };
+    use crate::game::mode::{Difficulty, GameMode};
+    use crate::game::stats::LastRun;
+
+    use super::*;
+
+    #[test]
+    fn quit_saves_the_pending_high_score_before_exiting() {
+        let path = std::env::temp_dir().join(format!(
+            "bevy_ball_game_quit_test_{}.ron",
+            std::process::id()
+        ));
+
+        let mut app = App::new();
+        app.add_state::<AppState>()
+            .add_event::<RunEnded>()
+            .add_event::<ButtonActivated>()
+            .add_event::<AppExit>()
+            .init_resource::<LastRun>()
+            .add_plugins((HighScoresPlugin, HighScoresFilePlugin))
+            .insert_resource(HighScoresFile {
+                path: Some(path.clone()),
+            })
+            .insert_resource(PendingHighScore {
+                entry: HighScoreEntry {
+                    name: String::new(),
+                    score: 42,
+                    date: 0,
+                    duration: 0.,
+                    seed: 0,
+                    mode: GameMode::Endless,
+                    difficulty: Difficulty::Normal,
+                },
+            })
+            .add_systems(
+                Update,
+                (
+                    handle_game_over_buttons,
+                    quit_after_high_score
+                        .run_if(resource_exists::<Quitting>())
+                        .run_if(not(resource_exists::<PendingHighScore>())),
+                ),
+            );
+        app.world.resource_mut::<PlayerName>().name = "Quitter".to_string();
+        let quit = app.world.spawn(GameOverButton::Quit).id();
+        app.world.send_event(ButtonActivated { entity: quit });
+
+        let mut updates = 0;
+        while app.world.resource::<Events<AppExit>>().is_empty() {
+            assert!(updates < 10, "the app never exited");
+            app.update();
+            updates += 1;
+        }
+
+        let saved = HighScores::read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let saved = saved.unwrap();
+        let table = saved.table(GameMode::Endless, Difficulty::Normal);
+        assert_eq!(table.len(), 1);
+        assert_eq!((table[0].name.as_str(), table[0].score), ("Quitter", 42));
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use crate::game::high_scores::{PendingHighScore, PlayerName};
+use crate::game::mode::GameMode;
+use crate::game::stats::LastRun;
+use crate::ui::{body_text_style, button_text_style, menu_style, spawn_button, title_text_style};
+
+use super::{GameOverButton, GameOverScreen, PlayerNameText};
+
+pub const GAME_OVER_BACKGROUND_COLOR: Color = Color::rgba(0.1, 0.02, 0.02, 0.9);
+
+pub fn spawn_game_over_screen(mut commands: Commands, last_run: Res<LastRun>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: menu_style(),
+                background_color: GAME_OVER_BACKGROUND_COLOR.into(),
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Game Over", title_text_style()));
+            parent.spawn(TextBundle::from_section(
+                format!("Score: {}", last_run.score),
+                button_text_style(),
+            ));
+            for line in [
+                format!("Survived {:.1}s", last_run.duration),
+                format!("Enemies in the arena: {}", last_run.enemies),
+                format!(
+                    "Stars collected: {}, enemies spawned: {}, bounces: {}",
+                    last_run.stats.stars_collected,
+                    last_run.stats.enemies_spawned,
+                    last_run.stats.enemy_bounces
+                ),
+                format!(
+                    "{} - {}, seed {}",
+                    last_run.mode, last_run.difficulty, last_run.seed
+                ),
+            ] {
+                parent.spawn(TextBundle::from_section(line, body_text_style()));
+            }
+            if last_run.mode == GameMode::Waves {
+                parent.spawn(TextBundle::from_section(
+                    format!("Waves cleared: {}", last_run.stats.waves_cleared),
+                    body_text_style(),
+                ));
+            }
+
+            match last_run.high_score_rank {
+                Some(rank) => {
+                    parent.spawn(TextBundle::from_section(
+                        format!("New high score! #{}", rank + 1),
+                        button_text_style(),
+                    ));
+                    parent.spawn((
+                        TextBundle::from_section("", body_text_style()),
+                        PlayerNameText,
+                    ));
+                }
+                None => {
+                    parent.spawn(TextBundle::from_section(
+                        "No new high score",
+                        body_text_style(),
+                    ));
+                }
+            }
+
+            spawn_button(parent, 0, "Restart", GameOverButton::Restart);
+            spawn_button(parent, 1, "Main Menu", GameOverButton::MainMenu);
+            spawn_button(parent, 2, "Quit", GameOverButton::Quit);
+        });
+}
+
+pub fn despawn_game_over_screen(
+    mut commands: Commands,
+    game_over_screen_query: Query<Entity, With<GameOverScreen>>,
+) {
+    for game_over_screen_entity in game_over_screen_query.iter() {
+        commands.entity(game_over_screen_entity).despawn_recursive();
+    }
+}
+
+/// Shows the name as it is typed, with a cursor, and what it was saved as.
+pub fn update_player_name_text(
+    player_name: Res<PlayerName>,
+    pending_high_score: Option<Res<PendingHighScore>>,
+    mut player_name_text_query: Query<&mut Text, With<PlayerNameText>>,
+) {
+    let value = if pending_high_score.is_some() {
+        format!("Name: {}_  (Enter to save)", player_name.name)
+    } else {
+        format!("Saved as {}", player_name.name)
+    };
+    for mut text in player_name_text_query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+mod interactions;
+mod layout;
+
+use bevy::prelude::*;
+
+use crate::game::high_scores::{PendingHighScore, PlayerName};
+use crate::ui::TextInput;
+use crate::AppState;
+
+use interactions::{
+    go_to_main_menu, handle_game_over_buttons, quit_after_high_score, type_player_name,
+};
+use layout::{despawn_game_over_screen, spawn_game_over_screen, update_player_name_text};
+
+/// Names longer than this are cut short, so they fit the high score table.
+pub const PLAYER_NAME_MAX_LENGTH: usize = 16;
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::GameOver),
+            (spawn_game_over_screen, start_name_entry),
+        )
+        .add_systems(
+            OnExit(AppState::GameOver),
+            (despawn_game_over_screen, stop_name_entry),
+        )
+        .add_systems(
+            Update,
+            (
+                type_player_name.run_if(resource_exists::<PendingHighScore>()),
+                handle_game_over_buttons,
+                go_to_main_menu,
+                update_player_name_text,
+            )
+                .chain()
+                .run_if(in_state(AppState::GameOver)),
+        )
+        .add_systems(
+            Update,
+            quit_after_high_score
+                .run_if(resource_exists::<Quitting>())
+                .run_if(not(resource_exists::<PendingHighScore>())),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct GameOverScreen;
+
+/// The text showing the name a new high score goes under.
+#[derive(Component)]
+pub struct PlayerNameText;
+
+/// Present from the Quit button until the app exits. Leaving by exiting never
+/// runs [`OnExit`], so the pending high score has to be entered before.
+#[derive(Resource)]
+pub struct Quitting;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverButton {
+    Restart,
+    MainMenu,
+    Quit,
+}
+
+/// A new high score waits for a name before the buttons take the keyboard.
+pub fn start_name_entry(
+    mut commands: Commands,
+    pending_high_score: Option<Res<PendingHighScore>>,
+    mut player_name: ResMut<PlayerName>,
+) {
+    if pending_high_score.is_some() {
+        player_name.name.truncate(PLAYER_NAME_MAX_LENGTH);
+        commands.insert_resource(TextInput);
+    }
+}
+
+pub fn stop_name_entry(mut commands: Commands) {
+    commands.remove_resource::<TextInput>();
+}
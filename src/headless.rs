@@ -0,0 +1,313 @@
+use bevy::{app::AppExit, input::InputPlugin, prelude::*, time::TimeUpdateStrategy};
+
+use crate::events::RunEnded;
+use crate::game::score::Score;
+use crate::game::{pause_simulation, resume_simulation};
+use crate::{handle_game_over, AppState};
+
+pub const HEADLESS_TIME_LIMIT: f32 = 600.;
+
+/// Runs the game logic without a window, renderer or audio device. Time
+/// advances by exactly one gameplay tick every update, so a run goes as fast
+/// as the machine allows and exits once the game is over.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(InputPlugin)
+            .add_systems(Startup, (step_time_by_ticks, start_game))
+            .add_systems(
+                OnEnter(AppState::Game),
+                (resume_simulation.after(pause_simulation),),
+            )
+            .add_systems(
+                Update,
+                (handle_game_over, exit_on_game_over, exit_on_time_limit),
+            );
+    }
+}
+
+pub fn step_time_by_ticks(mut commands: Commands, fixed_time: Res<FixedTime>) {
+    commands.insert_resource(TimeUpdateStrategy::ManualDuration(fixed_time.period));
+}
+
+pub fn start_game(mut next_app_state: ResMut<NextState<AppState>>) {
+    next_app_state.set(AppState::Game);
+}
+
+pub fn exit_on_game_over(
+    time: Res<Time>,
+    mut run_ended_event_reader: EventReader<RunEnded>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+) {
+    if run_ended_event_reader.iter().next().is_some() {
+        println!("Run lasted {:.2}s", time.elapsed_seconds());
+        app_exit_event_writer.send(AppExit);
+    }
+}
+
+pub fn exit_on_time_limit(
+    time: Res<Time>,
+    score: Option<Res<Score>>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+) {
+    if time.elapsed_seconds() >= HEADLESS_TIME_LIMIT {
+        if let Some(score) = score {
+            println!("Time limit reached: {}", score.value);
+        }
+        app_exit_event_writer.send(AppExit);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use bevy::ecs::event::ManualEventReader;
+
+    use crate::game::confinement::ConfinedToArena;
+    use crate::game::difficulty::DifficultyDirector;
+    use crate::game::enemy::{enemy_collider, Enemy, EnemyKind, EnemyScale};
+    use crate::game::enemy_kinds::{EnemyBehavior, Zigzagger};
+    use crate::game::player::Player;
+    use crate::game::power_ups::{ActivePowerUps, PowerUpKind};
+    use crate::game::rng::GameSeed;
+    use crate::game::stats::{LastRun, RunStats};
+    use crate::game::steering::{Steering, SteeringBehavior, SteeringTarget, WeightedBehavior};
+    use crate::game::tick::{
+        Position, PositionBundle, TickCount, TimeScale, Velocity, DEFAULT_TICK_RATE,
+    };
+    use crate::game::tuning::Tuning;
+    use crate::game::GamePlugin;
+
+    use super::*;
+
+    /// A headless game with the given seed and otherwise the default
+    /// settings, for tests to step through with [`App::update`].
+    pub fn headless_app(seed: u64) -> App {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugin)
+            .add_state::<AppState>()
+            .add_plugins(GamePlugin)
+            .insert_resource(GameSeed { seed: Some(seed) })
+            .insert_resource(FixedTime::new_from_secs(1. / DEFAULT_TICK_RATE));
+        app
+    }
+
+    /// A headless game one update in, with no enemies but the ones a test
+    /// places with [`spawn_test_enemy`].
+    pub fn headless_app_without_enemies(seed: u64) -> App {
+        let mut app = headless_app(seed);
+        app.insert_resource(Tuning {
+            number_of_enemies: 0,
+            enemy_spawn_duration: HEADLESS_TIME_LIMIT,
+            ..Default::default()
+        });
+        app.update();
+        app
+    }
+
+    /// Places a bouncer going at `velocity`, without the random heading
+    /// enemies spawn with.
+    pub fn spawn_test_enemy(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+        let tuning = app.world.resource::<Tuning>();
+        let collider = enemy_collider(
+            tuning,
+            tuning.enemy_kind(EnemyKind::Bouncer),
+            EnemyScale(1.),
+        );
+        app.world
+            .spawn((
+                PositionBundle::new(position),
+                collider,
+                ConfinedToArena::Bounce,
+                Enemy {},
+                EnemyKind::Bouncer,
+                EnemyScale(1.),
+                Velocity(velocity),
+            ))
+            .id()
+    }
+
+    /// Steps the app until the game is over and returns the score, or `None`
+    /// when the headless time limit comes first.
+    pub fn run_until_game_over(app: &mut App) -> Option<u32> {
+        let mut run_ended_event_reader = ManualEventReader::<RunEnded>::default();
+        let ticks = (HEADLESS_TIME_LIMIT * DEFAULT_TICK_RATE) as usize;
+        for _ in 0..ticks {
+            app.update();
+            let run_ended_events = app.world.resource::<Events<RunEnded>>();
+            if let Some(run_ended) = run_ended_event_reader.iter(run_ended_events).last() {
+                return Some(run_ended.score);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn time_advances_one_tick_per_update() {
+        let mut app = headless_app(1);
+        for _ in 0..100 {
+            app.update();
+        }
+
+        assert_eq!(app.world.resource::<TickCount>().ticks, 99);
+        assert_eq!(
+            app.world.resource::<State<AppState>>().get(),
+            &AppState::Game
+        );
+        assert_eq!(app.world.query::<&Player>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn same_seed_plays_out_the_same() {
+        let runs: Vec<_> = (0..2)
+            .map(|_| {
+                let mut app = headless_app(7);
+                let score = run_until_game_over(&mut app);
+                (score, app.world.resource::<LastRun>().clone())
+            })
+            .collect();
+
+        let (score, last_run) = &runs[0];
+        assert!(score.is_some());
+        assert_eq!(*score, runs[1].0);
+        assert_eq!(last_run.duration, runs[1].1.duration);
+        assert_eq!(last_run.enemies, runs[1].1.enemies);
+        assert_eq!(last_run.stats.enemy_bounces, runs[1].1.stats.enemy_bounces);
+    }
+
+    #[test]
+    fn enemies_bouncing_off_each_other_count_once() {
+        let mut app = headless_app_without_enemies(1);
+
+        // Heading at each other, well away from the walls and the player.
+        let speed = app.world.resource::<Tuning>().enemy_speed;
+        for (x, direction) in [(400., 1.), (600., -1.)] {
+            spawn_test_enemy(&mut app, Vec2::new(x, 150.), Vec2::X * direction * speed);
+        }
+        for _ in 0..DEFAULT_TICK_RATE as usize {
+            app.update();
+        }
+
+        assert_eq!(app.world.resource::<RunStats>().enemy_bounces, 1);
+    }
+
+    #[test]
+    fn shield_grace_runs_out_on_an_enemy_still_touching() {
+        let mut app = headless_app_without_enemies(1);
+
+        // A second enemy on the same spot, the one shield stops them both.
+        let position = app
+            .world
+            .query_filtered::<&Position, With<Player>>()
+            .single(&app.world)
+            .0;
+        for _ in 0..2 {
+            spawn_test_enemy(&mut app, position, Vec2::ZERO);
+        }
+        let tuning = app.world.resource::<Tuning>().clone();
+        app.world
+            .resource_mut::<ActivePowerUps>()
+            .start(PowerUpKind::Shield, tuning.power_ups.shield);
+        let grace_ticks = (tuning.power_ups.shield_grace * DEFAULT_TICK_RATE) as usize;
+        for _ in 0..grace_ticks / 2 {
+            app.update();
+        }
+
+        assert!(!app
+            .world
+            .resource::<ActivePowerUps>()
+            .is_active(PowerUpKind::Shield));
+        assert_eq!(app.world.query::<&Player>().iter(&app.world).count(), 1);
+        assert!(run_until_game_over(&mut app).is_some());
+    }
+
+    #[test]
+    fn enemies_spawn_slowed_during_slow_mo() {
+        let mut app = headless_app(1);
+        app.insert_resource(Tuning {
+            enemy_spawn_duration: 0.1,
+            ..Default::default()
+        });
+        app.update();
+
+        let tuning = app.world.resource::<Tuning>().clone();
+        app.world
+            .resource_mut::<ActivePowerUps>()
+            .start(PowerUpKind::SlowMo, tuning.power_ups.slow_mo);
+        for _ in 0..DEFAULT_TICK_RATE as usize {
+            app.update();
+            for time_scale in app
+                .world
+                .query_filtered::<Option<&TimeScale>, With<Enemy>>()
+                .iter(&app.world)
+            {
+                assert_eq!(
+                    time_scale.map(|time_scale| time_scale.0),
+                    Some(tuning.power_ups.slow_mo_time_scale)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn arriving_enemy_slows_down_while_enemies_speed_up() {
+        let mut app = headless_app_without_enemies(1);
+
+        // Arriving at a point in the bottom left, well away from the player.
+        let max_speed = app.world.resource::<DifficultyDirector>().enemy_speed;
+        let enemy = spawn_test_enemy(&mut app, Vec2::new(100., 100.), Vec2::ZERO);
+        app.world.entity_mut(enemy).insert(
+            Steering::new(
+                vec![WeightedBehavior::new(
+                    SteeringBehavior::Arrive {
+                        target: SteeringTarget::At(0.25, 0.25),
+                        slowing_distance: 150.,
+                    },
+                    1.,
+                )],
+                max_speed,
+            )
+            .with_max_force(4. * max_speed),
+        );
+        for _ in 0..3 * DEFAULT_TICK_RATE as usize {
+            app.update();
+        }
+
+        let director_speed = app.world.resource::<DifficultyDirector>().enemy_speed;
+        let (steering, velocity) = app
+            .world
+            .query::<(&Steering, &Velocity)>()
+            .get(&app.world, enemy)
+            .unwrap();
+        assert!(director_speed > max_speed);
+        assert_eq!(steering.max_speed, director_speed);
+        assert!(velocity.0.length() < steering.max_speed / 2.);
+    }
+
+    #[test]
+    fn enemies_switch_to_a_reloaded_behavior() {
+        let mut app = headless_app_without_enemies(1);
+
+        let enemy = spawn_test_enemy(&mut app, Vec2::new(200., 150.), Vec2::ZERO);
+        let mut tuning = app.world.resource_mut::<Tuning>();
+        let bouncer = tuning.enemy_kinds.get_mut(&EnemyKind::Bouncer).unwrap();
+        bouncer.behavior = EnemyBehavior::Chase { turn_rate: 1. };
+        app.update();
+
+        let steering = app.world.get::<Steering>(enemy).unwrap();
+        assert_eq!(steering.turn_rate, 1.);
+        assert!(steering.max_speed > 0.);
+
+        let mut tuning = app.world.resource_mut::<Tuning>();
+        let bouncer = tuning.enemy_kinds.get_mut(&EnemyKind::Bouncer).unwrap();
+        bouncer.behavior = EnemyBehavior::Zigzag {
+            angle: 0.5,
+            period: 1.,
+        };
+        app.update();
+
+        assert!(app.world.get::<Steering>(enemy).is_none());
+        assert!(app.world.get::<Zigzagger>(enemy).is_some());
+    }
+}
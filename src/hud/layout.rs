@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::game::power_ups::PowerUpKind;
+use crate::ui::{body_text_style, title_text_style};
+
+use super::{Hud, HudText, WaveBanner};
+
+pub const HUD_BACKGROUND_COLOR: Color = Color::rgba(0., 0., 0., 0.5);
+
+pub fn spawn_hud(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.),
+                    left: Val::Px(0.),
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                background_color: HUD_BACKGROUND_COLOR.into(),
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            for hud_text in [
+                HudText::Score,
+                HudText::BestScore,
+                HudText::Time,
+                HudText::Enemies,
+                HudText::Stars,
+                HudText::NextEnemy,
+            ] {
+                parent.spawn((TextBundle::from_section("", body_text_style()), hud_text));
+            }
+        });
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(0.),
+                    left: Val::Px(0.),
+                    column_gap: Val::Px(24.),
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            for kind in PowerUpKind::ALL {
+                parent.spawn((
+                    TextBundle::from_section("", body_text_style()),
+                    HudText::PowerUp(kind),
+                ));
+            }
+        });
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(30.),
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("", title_text_style()),
+                WaveBanner { remaining: 0. },
+            ));
+        });
+}
+
+pub fn despawn_hud(mut commands: Commands, hud_query: Query<Entity, With<Hud>>) {
+    for hud_entity in hud_query.iter() {
+        commands.entity(hud_entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,59 @@
+mod layout;
+mod updates;
+
+use bevy::prelude::*;
+
+use crate::game::power_ups::{ActivePowerUps, PowerUpKind};
+use crate::game::tick::TickCount;
+use crate::AppState;
+
+use layout::{despawn_hud, spawn_hud};
+use updates::{
+    show_wave_banner, update_count_texts, update_power_up_texts, update_score_texts,
+    update_timer_texts,
+};
+
+/// Score, best score, run time, what is in the arena and the power-ups
+/// running, shown over the game.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Game), (spawn_hud,))
+            .add_systems(OnExit(AppState::Game), (despawn_hud,))
+            .add_systems(
+                Update,
+                (
+                    update_score_texts,
+                    update_timer_texts.run_if(resource_changed::<TickCount>()),
+                    update_count_texts,
+                    show_wave_banner,
+                    update_power_up_texts.run_if(resource_changed::<ActivePowerUps>()),
+                )
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct Hud;
+
+/// Announces each wave in the middle of the screen for a moment.
+#[derive(Component, Debug)]
+pub struct WaveBanner {
+    /// Seconds left before it is hidden.
+    pub remaining: f32,
+}
+
+/// What a piece of HUD text shows.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HudText {
+    Score,
+    BestScore,
+    Time,
+    Enemies,
+    Stars,
+    NextEnemy,
+    /// Seconds left on a power-up, empty while it is not running.
+    PowerUp(PowerUpKind),
+}
@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+
+use crate::events::{RunStarted, StarCollected, WaveCleared, WaveStarted};
+use crate::game::enemy::{Enemy, EnemySpawnTimer};
+use crate::game::high_scores::HighScores;
+use crate::game::mode::{Difficulty, GameMode};
+use crate::game::power_ups::{ActivePowerUps, PowerUpKind};
+use crate::game::star::Star;
+use crate::game::tick::{tick_seconds, TickCount};
+use crate::game::waves::WaveState;
+
+use super::{HudText, WaveBanner};
+
+pub const WAVE_BANNER_DURATION: f32 = 2.;
+
+/// Sets the text shown by every `HudText` of the given kind, leaving it alone
+/// when nothing changed so the UI is not laid out again for nothing.
+fn set_hud_text(text_query: &mut Query<(&HudText, &mut Text)>, kind: HudText, value: String) {
+    for (hud_text, mut text) in text_query.iter_mut() {
+        if *hud_text == kind && text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// Follows the score from the gameplay events. The best score counts the
+/// current game once it beats the table.
+pub fn update_score_texts(
+    mut run_started_event_reader: EventReader<RunStarted>,
+    mut star_collected_event_reader: EventReader<StarCollected>,
+    mut score: Local<u32>,
+    high_scores: Res<HighScores>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    mut text_query: Query<(&HudText, &mut Text)>,
+) {
+    let run_started = run_started_event_reader.iter().last().is_some();
+    if run_started {
+        *score = 0;
+    }
+    let star_collected = star_collected_event_reader.iter().last();
+    if let Some(star_collected) = star_collected {
+        *score = star_collected.score;
+    }
+    if !run_started && star_collected.is_none() && !high_scores.is_changed() {
+        return;
+    }
+
+    let score = *score;
+    let best_score = high_scores
+        .table(*game_mode, *difficulty)
+        .first()
+        .map_or(0, |entry| entry.score)
+        .max(score);
+
+    set_hud_text(&mut text_query, HudText::Score, format!("Score: {score}"));
+    set_hud_text(
+        &mut text_query,
+        HudText::BestScore,
+        format!("Best: {best_score}"),
+    );
+}
+
+pub fn update_timer_texts(
+    tick_count: Res<TickCount>,
+    fixed_time: Res<FixedTime>,
+    enemy_spawn_timer: Res<EnemySpawnTimer>,
+    game_mode: Res<GameMode>,
+    wave_state: Res<WaveState>,
+    mut text_query: Query<(&HudText, &mut Text)>,
+) {
+    let elapsed = tick_count.ticks as f32 * tick_seconds(&fixed_time);
+    set_hud_text(
+        &mut text_query,
+        HudText::Time,
+        format!("Time: {elapsed:.1}s"),
+    );
+    set_hud_text(
+        &mut text_query,
+        HudText::NextEnemy,
+        match *game_mode {
+            GameMode::Endless => format!(
+                "Next enemy: {:.1}s",
+                enemy_spawn_timer.timer.remaining_secs()
+            ),
+            GameMode::Waves => format!("Wave: {}", wave_state.number),
+        },
+    );
+}
+
+pub fn update_count_texts(
+    enemy_query: Query<(), With<Enemy>>,
+    star_query: Query<(), With<Star>>,
+    mut text_query: Query<(&HudText, &mut Text)>,
+) {
+    set_hud_text(
+        &mut text_query,
+        HudText::Enemies,
+        format!("Enemies: {}", enemy_query.iter().count()),
+    );
+    set_hud_text(
+        &mut text_query,
+        HudText::Stars,
+        format!("Stars: {}", star_query.iter().count()),
+    );
+}
+
+pub fn update_power_up_texts(
+    active_power_ups: Res<ActivePowerUps>,
+    mut text_query: Query<(&HudText, &mut Text)>,
+) {
+    for kind in PowerUpKind::ALL {
+        let value = active_power_ups
+            .remaining
+            .get(&kind)
+            .map_or(String::new(), |remaining| {
+                format!("{kind}: {remaining:.1}s")
+            });
+        set_hud_text(&mut text_query, HudText::PowerUp(kind), value);
+    }
+}
+
+pub fn show_wave_banner(
+    mut wave_started_event_reader: EventReader<WaveStarted>,
+    mut wave_cleared_event_reader: EventReader<WaveCleared>,
+    time: Res<Time>,
+    mut banner_query: Query<(&mut WaveBanner, &mut Text)>,
+) {
+    let announcement = wave_cleared_event_reader
+        .iter()
+        .last()
+        .map(|wave_cleared| format!("Wave {} cleared", wave_cleared.number));
+    let announcement = wave_started_event_reader
+        .iter()
+        .last()
+        .map(|wave_started| format!("Wave {}", wave_started.number))
+        .or(announcement);
+
+    for (mut banner, mut text) in banner_query.iter_mut() {
+        if let Some(announcement) = &announcement {
+            text.sections[0].value = announcement.clone();
+            banner.remaining = WAVE_BANNER_DURATION;
+        } else if banner.remaining > 0. {
+            banner.remaining -= time.delta_seconds();
+            if banner.remaining <= 0. {
+                text.sections[0].value.clear();
+            }
+        }
+    }
+}
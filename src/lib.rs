@@ -0,0 +1,4 @@
+//! The parts of the game that do not need the rest of it, shared with the
+//! benchmarks.
+
+pub mod spatial_hash;
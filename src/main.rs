@@ -1,28 +1,218 @@
 pub mod events;
 pub mod game;
+pub mod game_over;
+pub mod headless;
+pub mod hud;
 pub mod main_menu;
+pub mod pause_menu;
+pub mod settings;
+pub mod ui;
 
-use bevy::{app::AppExit, prelude::*, window::PrimaryWindow};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::{asset::ChangeWatcher, prelude::*, render::camera::ScalingMode};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use clap::{value_parser, Arg, ArgAction, Command};
 
-use events::GameOver;
-use game::GamePlugin;
+use events::RunEnded;
+use game::actions::InputMapFilePlugin;
+use game::arena::Arena;
+use game::audio::GameAudioPlugin;
+use game::high_scores::HighScoresFilePlugin;
+use game::input::PointerInputPlugin;
+use game::mode::GameMode;
+use game::music::MusicPlugin;
+use game::particles::ParticlesPlugin;
+use game::replay::{Replay, ReplayPlaybackPlugin, ReplayRecordingPlugin};
+use game::rng::GameSeed;
+use game::sprites::GameSpritesPlugin;
+use game::tick::DEFAULT_TICK_RATE;
+use game::tuning::{read_tuning_file, TuningAssetPlugin};
+use game::waves::{read_waves_file, WavesAssetPlugin};
+use game::{pause_simulation, resume_simulation, GamePlugin};
+use game_over::GameOverPlugin;
+use headless::{start_game, HeadlessPlugin};
+use hud::HudPlugin;
 use main_menu::MainMenuPlugin;
-
-use crate::game::SimulationState;
+use pause_menu::PauseMenuPlugin;
+use settings::SettingsPlugin;
+use ui::UiPlugin;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_state::<AppState>()
-        .add_plugins(MainMenuPlugin)
-        .add_plugins(GamePlugin)
-        .add_systems(Startup, spawn_camera)
-        .add_systems(Update, (transition_to_game_state, transition_to_main_menu))
-        .add_systems(Update, (handle_game_over,))
-        .add_systems(Update, (exit_game,))
-        .add_plugins(WorldInspectorPlugin::new())
-        .run();
+    let args = command().get_matches();
+    let game_seed = GameSeed {
+        seed: args.get_one::<u64>("seed").copied(),
+    };
+    let arena = args
+        .get_one::<Arena>("arena-size")
+        .copied()
+        .unwrap_or_default();
+    let tick_rate = args
+        .get_one::<f32>("tick-rate")
+        .copied()
+        .unwrap_or(DEFAULT_TICK_RATE);
+    let game_mode = args
+        .get_one::<GameMode>("mode")
+        .copied()
+        .unwrap_or_default();
+    let record_path = args.get_one::<PathBuf>("record").cloned();
+    let replay = args.get_one::<PathBuf>("replay").map(|path| {
+        Replay::read(path).unwrap_or_else(|error| panic!("{}: {error}", path.display()))
+    });
+
+    if args.get_flag("headless") {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugin)
+            .add_state::<AppState>()
+            .add_plugins(GamePlugin)
+            .insert_resource(game_seed)
+            .insert_resource(game_mode)
+            .insert_resource(arena)
+            .insert_resource(FixedTime::new_from_secs(1. / tick_rate));
+        if replay.is_none() {
+            app.add_systems(Startup, (read_tuning_file, read_waves_file));
+        }
+        add_replay_plugins(&mut app, record_path, replay);
+        app.run();
+        return;
+    }
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(AssetPlugin {
+        watch_for_changes: ChangeWatcher::with_delay(Duration::from_millis(200)),
+        ..Default::default()
+    }))
+    .add_state::<AppState>()
+    .add_plugins(UiPlugin)
+    .add_plugins(MainMenuPlugin)
+    .add_plugins(GameOverPlugin)
+    .add_plugins(HudPlugin)
+    .add_plugins(PauseMenuPlugin)
+    .add_plugins(SettingsPlugin)
+    .add_plugins(GamePlugin)
+    .add_plugins(GameSpritesPlugin)
+    .add_plugins(ParticlesPlugin)
+    .add_plugins(GameAudioPlugin)
+    .add_plugins(MusicPlugin)
+    .add_plugins(HighScoresFilePlugin)
+    .add_plugins(InputMapFilePlugin)
+    .add_plugins(PointerInputPlugin)
+    .insert_resource(game_seed)
+    .insert_resource(game_mode)
+    .insert_resource(arena)
+    .insert_resource(FixedTime::new_from_secs(1. / tick_rate))
+    .add_systems(Startup, (spawn_camera,))
+    .add_systems(Update, (fit_camera_to_arena,))
+    .add_systems(Update, (handle_game_over,))
+    .add_plugins(WorldInspectorPlugin::new());
+    if let Some(replay) = &replay {
+        // Watching a replay goes straight into the game, and its own pause
+        // changes take over from there.
+        app.add_systems(Startup, (start_game,)).add_systems(
+            OnEnter(AppState::Game),
+            (resume_simulation.after(pause_simulation),),
+        );
+        println!("Playing back a replay with seed {}", replay.seed);
+    } else {
+        // The replay brings its own tuning and waves, the files must not
+        // override them.
+        app.add_plugins(TuningAssetPlugin)
+            .add_plugins(WavesAssetPlugin);
+    }
+    add_replay_plugins(&mut app, record_path, replay);
+    app.run();
+}
+
+/// Adds the replay recorder and player asked for on the command line. The
+/// player overrides the seed, arena, tick rate and tuning, so it has to come
+/// after they are set.
+fn add_replay_plugins(app: &mut App, record_path: Option<PathBuf>, replay: Option<Replay>) {
+    if let Some(path) = record_path {
+        app.add_plugins(ReplayRecordingPlugin { path });
+    }
+    if let Some(replay) = replay {
+        app.add_plugins(ReplayPlaybackPlugin { replay });
+    }
+}
+
+/// The command line options, all optional.
+fn command() -> Command {
+    Command::new("bevy_ball_game")
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .value_parser(value_parser!(u64))
+                .help("Seed for the game's randomness, random if not given"),
+        )
+        .arg(
+            Arg::new("arena-size")
+                .long("arena-size")
+                .value_name("WIDTHxHEIGHT")
+                .value_parser(parse_arena_size)
+                .help("Size of the arena in game units, such as 1280x720"),
+        )
+        .arg(
+            Arg::new("tick-rate")
+                .long("tick-rate")
+                .value_name("TICKS")
+                .value_parser(parse_tick_rate)
+                .help("Game logic ticks per second"),
+        )
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .value_name("MODE")
+                .value_parser(|mode: &str| mode.parse::<GameMode>())
+                .help("Game mode, endless or waves"),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Records the run to a replay file"),
+        )
+        .arg(
+            Arg::new("replay")
+                .long("replay")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Plays back a recorded replay file"),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .action(ArgAction::SetTrue)
+                .help("Runs the game logic without a window"),
+        )
+}
+
+/// Both sides have to be numbers above zero, for the arena to have room in it
+/// and for the camera to scale it to the window.
+fn parse_arena_size(size: &str) -> Result<Arena, String> {
+    let (width, height) = size.split_once('x').ok_or("must look like 1280x720")?;
+    let side = |side: &str, name: &str| match side.parse::<f32>() {
+        Ok(length) if length.is_finite() && length > 0. => Ok(length),
+        Ok(_) => Err(format!("{name} must be above zero")),
+        Err(_) => Err(format!("{name} must be a number")),
+    };
+    Ok(Arena {
+        width: side(width, "width")?,
+        height: side(height, "height")?,
+    })
+}
+
+/// The tick length is one over the rate, so the rate has to be a number
+/// above zero.
+fn parse_tick_rate(tick_rate: &str) -> Result<f32, String> {
+    let tick_rate: f32 = tick_rate.parse().map_err(|_| "must be a number")?;
+    if tick_rate.is_finite() && tick_rate > 0. {
+        Ok(tick_rate)
+    } else {
+        Err("must be above zero".to_string())
+    }
 }
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
@@ -33,58 +223,35 @@ pub enum AppState {
     GameOver,
 }
 
-pub fn spawn_camera(mut commands: Commands, window_query: Query<&Window, With<PrimaryWindow>>) {
-    let window = window_query.get_single().unwrap();
-
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(window.width() / 2., window.height() / 2., 0.),
-        ..Default::default()
-    });
+pub fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
 }
 
-pub fn exit_game(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut app_exit_event_writer: EventWriter<AppExit>,
+/// Keeps the whole arena in view and centered, scaled to fit the window
+/// whatever its size.
+pub fn fit_camera_to_arena(
+    arena: Res<Arena>,
+    mut camera_query: Query<(Ref<Camera>, &mut Transform, &mut OrthographicProjection)>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
-        app_exit_event_writer.send(AppExit)
+    for (camera, mut transform, mut projection) in camera_query.iter_mut() {
+        if arena.is_changed() || camera.is_added() {
+            let center = arena.center();
+            transform.translation.x = center.x;
+            transform.translation.y = center.y;
+            projection.scaling_mode = ScalingMode::AutoMin {
+                min_width: arena.width,
+                min_height: arena.height,
+            };
+        }
     }
 }
 
 pub fn handle_game_over(
     mut next_app_state: ResMut<NextState<AppState>>,
-    mut game_over_event_reader: EventReader<GameOver>,
+    mut run_ended_event_reader: EventReader<RunEnded>,
 ) {
-    game_over_event_reader.iter().for_each(|event| {
+    run_ended_event_reader.iter().for_each(|event| {
         println!("Game Over: {}", event.score);
         next_app_state.set(AppState::GameOver)
     })
 }
-
-pub fn transition_to_game_state(
-    keyboard_input: Res<Input<KeyCode>>,
-    app_state: Res<State<AppState>>,
-    mut next_app_state: ResMut<NextState<AppState>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::G) {
-        if app_state.get() != &AppState::Game {
-            next_app_state.set(AppState::Game);
-            println!("entered app state game")
-        }
-    }
-}
-
-pub fn transition_to_main_menu(
-    keyboard_input: Res<Input<KeyCode>>,
-    app_state: Res<State<AppState>>,
-    mut next_app_state: ResMut<NextState<AppState>>,
-    mut next_simulation_state: ResMut<NextState<SimulationState>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::M) {
-        if app_state.get() != &AppState::MainMenu {
-            next_app_state.set(AppState::MainMenu);
-            next_simulation_state.set(SimulationState::Paused);
-            println!("entered app state main menu")
-        }
-    }
-}
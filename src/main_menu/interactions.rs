@@ -0,0 +1,200 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::game::actions::{
+    Action, ActionState, AxisDirection, Binding, InputMap, InputMapChanged,
+};
+use crate::game::input::ControlScheme;
+use crate::game::mode::{Difficulty, GameMode};
+use crate::ui::{ButtonActivated, TextInput};
+use crate::AppState;
+
+use super::{MainMenuButton, MainMenuPage, Rebinding};
+
+/// Sticks and triggers that can be bound, pushed at least this far.
+const REBIND_AXES: [GamepadAxisType; 6] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::RightStickX,
+    GamepadAxisType::RightStickY,
+    GamepadAxisType::LeftZ,
+    GamepadAxisType::RightZ,
+];
+const REBIND_AXIS_THRESHOLD: f32 = 0.5;
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_main_menu_buttons(
+    mut commands: Commands,
+    mut button_activated_event_reader: EventReader<ButtonActivated>,
+    button_query: Query<&MainMenuButton>,
+    mut main_menu_page: ResMut<MainMenuPage>,
+    mut rebinding: ResMut<Rebinding>,
+    mut input_map: ResMut<InputMap>,
+    mut input_map_changed_event_writer: EventWriter<InputMapChanged>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+    mut game_mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut control_scheme: ResMut<ControlScheme>,
+) {
+    for event in button_activated_event_reader.iter() {
+        match button_query.get(event.entity) {
+            Ok(MainMenuButton::Play) => next_app_state.set(AppState::Game),
+            Ok(MainMenuButton::HighScores) => {
+                *main_menu_page = MainMenuPage::HighScores(*game_mode, *difficulty)
+            }
+            Ok(MainMenuButton::Settings) => *main_menu_page = MainMenuPage::Settings,
+            Ok(MainMenuButton::Quit) => app_exit_event_writer.send(AppExit),
+            Ok(MainMenuButton::Back) => {
+                *main_menu_page = MainMenuPage::Main;
+                if rebinding.action.take().is_some() {
+                    commands.remove_resource::<TextInput>();
+                }
+            }
+            Ok(MainMenuButton::NextHighScoresMode) => {
+                if let MainMenuPage::HighScores(mode, table_difficulty) = *main_menu_page {
+                    *main_menu_page = MainMenuPage::HighScores(mode.next(), table_difficulty);
+                }
+            }
+            Ok(MainMenuButton::NextHighScoresDifficulty) => {
+                if let MainMenuPage::HighScores(mode, table_difficulty) = *main_menu_page {
+                    *main_menu_page = MainMenuPage::HighScores(mode, table_difficulty.next());
+                }
+            }
+            Ok(MainMenuButton::NextGameMode) => *game_mode = game_mode.next(),
+            Ok(MainMenuButton::NextDifficulty) => *difficulty = difficulty.next(),
+            Ok(MainMenuButton::NextControlScheme) => *control_scheme = control_scheme.next(),
+            Ok(MainMenuButton::Controls) => *main_menu_page = MainMenuPage::Controls,
+            Ok(MainMenuButton::Rebind(action)) => {
+                rebinding.action = Some(*action);
+                commands.insert_resource(TextInput);
+            }
+            Ok(MainMenuButton::ResetControls) => {
+                *input_map = InputMap::default();
+                input_map_changed_event_writer.send(InputMapChanged);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Back leaves a sub page for the main page, and the game from the main page.
+/// Does nothing while the player picks a new binding, Escape then cancels it.
+pub fn go_back(
+    action_state: Res<ActionState>,
+    rebinding: Res<Rebinding>,
+    mut main_menu_page: ResMut<MainMenuPage>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+) {
+    if action_state.just_pressed(Action::Back) && rebinding.action.is_none() {
+        if *main_menu_page == MainMenuPage::Main {
+            app_exit_event_writer.send(AppExit);
+        } else {
+            *main_menu_page = MainMenuPage::Main;
+        }
+    }
+}
+
+/// Binds the first key, gamepad button or stick pushed after a rebind button
+/// was activated. Starts on the frame after, so the press that activated the
+/// button is not taken as the new binding. Escape cancels instead of being
+/// bound, so it always gets the player out of the menus.
+#[allow(clippy::too_many_arguments)]
+pub fn capture_rebinding(
+    mut commands: Commands,
+    mut rebinding: ResMut<Rebinding>,
+    mut input_map: ResMut<InputMap>,
+    mut input_map_changed_event_writer: EventWriter<InputMapChanged>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+    let action = match rebinding.action {
+        Some(action) => action,
+        None => return,
+    };
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        rebinding.action = None;
+        commands.remove_resource::<TextInput>();
+        return;
+    }
+
+    let pushed_axis = gamepads.iter().find_map(|gamepad| {
+        REBIND_AXES.into_iter().find_map(|axis_type| {
+            let value = gamepad_axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.);
+            if value >= REBIND_AXIS_THRESHOLD {
+                Some(Binding::GamepadAxis(axis_type, AxisDirection::Positive))
+            } else if value <= -REBIND_AXIS_THRESHOLD {
+                Some(Binding::GamepadAxis(axis_type, AxisDirection::Negative))
+            } else {
+                None
+            }
+        })
+    });
+    let binding = keyboard_input
+        .get_just_pressed()
+        .next()
+        .map(|key_code| Binding::Key(*key_code))
+        .or_else(|| {
+            gamepad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::GamepadButton(button.button_type))
+        })
+        .or(pushed_axis);
+
+    if let Some(binding) = binding {
+        input_map.rebind(action, binding);
+        input_map_changed_event_writer.send(InputMapChanged);
+        rebinding.action = None;
+        commands.remove_resource::<TextInput>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::actions::update_action_state;
+
+    use super::*;
+
+    #[test]
+    fn escape_cancels_a_rebinding_and_stays_on_the_page() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Gamepads>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<InputMap>()
+            .init_resource::<ActionState>()
+            .add_event::<InputMapChanged>()
+            .add_event::<AppExit>()
+            .insert_resource(MainMenuPage::Controls)
+            .insert_resource(Rebinding {
+                action: Some(Action::Pause),
+            })
+            .insert_resource(TextInput)
+            .add_systems(
+                Update,
+                (update_action_state, go_back, capture_rebinding).chain(),
+            );
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Escape);
+        app.update();
+
+        assert_eq!(
+            *app.world.resource::<MainMenuPage>(),
+            MainMenuPage::Controls
+        );
+        assert_eq!(app.world.resource::<Rebinding>().action, None);
+        assert!(!app.world.contains_resource::<TextInput>());
+        assert_eq!(
+            app.world.resource::<InputMap>().bindings(Action::Pause),
+            InputMap::default().bindings(Action::Pause)
+        );
+        assert!(app.world.resource::<Events<InputMapChanged>>().is_empty());
+        assert!(app.world.resource::<Events<AppExit>>().is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+
+use crate::game::actions::{Action, InputMap};
+use crate::game::audio::AudioSettings;
+use crate::game::high_scores::HighScores;
+use crate::game::input::ControlScheme;
+use crate::game::mode::{Difficulty, GameMode};
+use crate::settings::spawn_audio_settings;
+use crate::ui::{
+    body_text_style, menu_style, remember_focus, spawn_button, spawn_small_button,
+    title_text_style, Focusable, Focused,
+};
+
+use super::{MainMenu, MainMenuButton, MainMenuPage, Rebinding};
+
+pub const MAIN_MENU_BACKGROUND_COLOR: Color = Color::rgb(0.05, 0.05, 0.1);
+
+/// Replaces whatever page is shown with the current one.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    main_menu_query: Query<Entity, With<MainMenu>>,
+    focused_query: Query<&Focusable, With<Focused>>,
+    main_menu_page: Res<MainMenuPage>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    high_scores: Res<HighScores>,
+    input_map: Res<InputMap>,
+    rebinding: Res<Rebinding>,
+    control_scheme: Res<ControlScheme>,
+    audio_settings: Res<AudioSettings>,
+) {
+    if !main_menu_page.is_changed() {
+        remember_focus(&mut commands, &focused_query);
+    }
+    for main_menu_entity in main_menu_query.iter() {
+        commands.entity(main_menu_entity).despawn_recursive();
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: menu_style(),
+                background_color: MAIN_MENU_BACKGROUND_COLOR.into(),
+                ..default()
+            },
+            MainMenu,
+        ))
+        .with_children(|parent| match *main_menu_page {
+            MainMenuPage::Main => {
+                parent.spawn(TextBundle::from_section(
+                    "Bevy Ball Game",
+                    title_text_style(),
+                ));
+                spawn_button(parent, 0, "Play", MainMenuButton::Play);
+                spawn_button(parent, 1, "High Scores", MainMenuButton::HighScores);
+                spawn_button(parent, 2, "Settings", MainMenuButton::Settings);
+                spawn_button(parent, 3, "Quit", MainMenuButton::Quit);
+            }
+            MainMenuPage::HighScores(mode, table_difficulty) => {
+                parent.spawn(TextBundle::from_section("High Scores", title_text_style()));
+                spawn_button(
+                    parent,
+                    0,
+                    format!("{mode}"),
+                    MainMenuButton::NextHighScoresMode,
+                );
+                spawn_button(
+                    parent,
+                    1,
+                    format!("{table_difficulty}"),
+                    MainMenuButton::NextHighScoresDifficulty,
+                );
+                let table = high_scores.table(mode, table_difficulty);
+                if table.is_empty() {
+                    parent.spawn(TextBundle::from_section("No scores yet", body_text_style()));
+                }
+                for (rank, entry) in table.iter().enumerate() {
+                    parent.spawn(TextBundle::from_section(
+                        format!(
+                            "{}. {}  {}  {:.0}s  {}",
+                            rank + 1,
+                            entry.name,
+                            entry.score,
+                            entry.duration,
+                            entry.date_string()
+                        ),
+                        body_text_style(),
+                    ));
+                }
+                spawn_button(parent, 2, "Back", MainMenuButton::Back);
+            }
+            MainMenuPage::Settings => {
+                parent.spawn(TextBundle::from_section("Settings", title_text_style()));
+                spawn_small_button(
+                    parent,
+                    0,
+                    format!("Mode: {}", *game_mode),
+                    MainMenuButton::NextGameMode,
+                );
+                spawn_small_button(
+                    parent,
+                    1,
+                    format!("Difficulty: {}", *difficulty),
+                    MainMenuButton::NextDifficulty,
+                );
+                spawn_small_button(
+                    parent,
+                    2,
+                    format!("Movement: {}", *control_scheme),
+                    MainMenuButton::NextControlScheme,
+                );
+                let index = spawn_audio_settings(parent, 3, &audio_settings);
+                spawn_small_button(parent, index, "Controls", MainMenuButton::Controls);
+                spawn_small_button(parent, index + 1, "Back", MainMenuButton::Back);
+            }
+            MainMenuPage::Controls => {
+                parent.spawn(TextBundle::from_section("Controls", title_text_style()));
+                for (index, action) in Action::ALL.into_iter().enumerate() {
+                    let label = if rebinding.action == Some(action) {
+                        format!("{action}: press a key or button, Escape to cancel")
+                    } else {
+                        let bindings: Vec<String> = input_map
+                            .bindings(action)
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect();
+                        format!("{action}: {}", bindings.join(", "))
+                    };
+                    spawn_small_button(parent, index, label, MainMenuButton::Rebind(action));
+                }
+                spawn_small_button(
+                    parent,
+                    Action::ALL.len(),
+                    "Reset to Defaults",
+                    MainMenuButton::ResetControls,
+                );
+                spawn_small_button(parent, Action::ALL.len() + 1, "Back", MainMenuButton::Back);
+            }
+        });
+}
+
+pub fn despawn_main_menu(mut commands: Commands, main_menu_query: Query<Entity, With<MainMenu>>) {
+    for main_menu_entity in main_menu_query.iter() {
+        commands.entity(main_menu_entity).despawn_recursive();
+    }
+}
@@ -1,37 +1,93 @@
+mod interactions;
+mod layout;
+
 use bevy::prelude::*;
 
+use crate::game::actions::{Action, InputMap};
+use crate::game::audio::AudioSettings;
+use crate::game::high_scores::HighScores;
+use crate::game::input::ControlScheme;
+use crate::game::mode::{Difficulty, GameMode};
 use crate::AppState;
 
+use interactions::{capture_rebinding, go_back, handle_main_menu_buttons};
+use layout::{despawn_main_menu, spawn_main_menu};
+
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::MainMenu), spawn_main_menu)
-            .add_systems(OnExit(AppState::MainMenu), despawn_main_menu);
+        app.init_resource::<MainMenuPage>()
+            .init_resource::<Rebinding>()
+            .add_systems(OnEnter(AppState::MainMenu), (show_main_page,))
+            .add_systems(OnExit(AppState::MainMenu), (despawn_main_menu,))
+            .add_systems(
+                Update,
+                (
+                    // Before the rebinding is captured, so the Escape that
+                    // cancels it does not also leave the page.
+                    go_back,
+                    capture_rebinding,
+                    handle_main_menu_buttons,
+                    spawn_main_menu.run_if(
+                        resource_changed::<MainMenuPage>()
+                            .or_else(resource_changed::<GameMode>())
+                            .or_else(resource_changed::<Difficulty>())
+                            .or_else(resource_changed::<ControlScheme>())
+                            .or_else(resource_changed::<AudioSettings>())
+                            .or_else(resource_changed::<HighScores>())
+                            .or_else(resource_changed::<InputMap>())
+                            .or_else(resource_changed::<Rebinding>()),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::MainMenu)),
+            );
     }
 }
 
 #[derive(Component)]
 pub struct MainMenu;
 
-pub fn spawn_main_menu(mut commands: Commands) {
-    println!("Game main menu");
-    commands.spawn((
-        NodeBundle {
-            style: Style {
-                width: Val::Percent(100.),
-                height: Val::Percent(100.),
-                ..Default::default()
-            },
-            background_color: Color::rgb_u8(255, 200, 200).into(),
-            ..default()
-        },
-        MainMenu,
-    ));
+/// Which page of the main menu is shown.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MainMenuPage {
+    #[default]
+    Main,
+    /// The table of the given mode and difficulty.
+    HighScores(GameMode, Difficulty),
+    Settings,
+    Controls,
 }
 
-pub fn despawn_main_menu(mut commands: Commands, main_menu_query: Query<Entity, With<MainMenu>>) {
-    if let Ok(main_menu_entity) = main_menu_query.get_single() {
-        commands.entity(main_menu_entity).despawn_recursive();
-    }
+/// The action waiting for the player to press its new key or button.
+#[derive(Resource, Debug, Default)]
+pub struct Rebinding {
+    pub action: Option<Action>,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MainMenuButton {
+    Play,
+    HighScores,
+    Settings,
+    Quit,
+    Back,
+    /// Shows the high score table of the next mode.
+    NextHighScoresMode,
+    /// Shows the high score table of the next difficulty.
+    NextHighScoresDifficulty,
+    /// Changes the mode of the next games.
+    NextGameMode,
+    /// Changes the difficulty of the next games.
+    NextDifficulty,
+    NextControlScheme,
+    Controls,
+    /// Waits for a new key or button for the action.
+    Rebind(Action),
+    ResetControls,
+}
+
+pub fn show_main_page(mut main_menu_page: ResMut<MainMenuPage>) {
+    *main_menu_page = MainMenuPage::Main;
 }
@@ -0,0 +1,58 @@
+use bevy::{prelude::*, window::WindowFocused};
+
+use crate::game::actions::{Action, ActionState};
+use crate::game::input::TogglePause;
+use crate::game::RestartGame;
+use crate::ui::ButtonActivated;
+use crate::AppState;
+
+use super::{PauseMenuButton, PauseMenuPage};
+
+pub fn handle_pause_menu_buttons(
+    mut button_activated_event_reader: EventReader<ButtonActivated>,
+    button_query: Query<&PauseMenuButton>,
+    mut pause_menu_page: ResMut<PauseMenuPage>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut toggle_pause_event_writer: EventWriter<TogglePause>,
+    mut restart_game_event_writer: EventWriter<RestartGame>,
+) {
+    for event in button_activated_event_reader.iter() {
+        match button_query.get(event.entity) {
+            Ok(PauseMenuButton::Resume) => toggle_pause_event_writer.send(TogglePause),
+            Ok(PauseMenuButton::Restart) => restart_game_event_writer.send(RestartGame),
+            Ok(PauseMenuButton::Settings) => *pause_menu_page = PauseMenuPage::Settings,
+            Ok(PauseMenuButton::QuitToMenu) => next_app_state.set(AppState::MainMenu),
+            Ok(PauseMenuButton::Back) => *pause_menu_page = PauseMenuPage::Main,
+            Err(_) => {}
+        }
+    }
+}
+
+/// Back leaves a sub page for the main page, and resumes from the main page.
+pub fn go_back(
+    action_state: Res<ActionState>,
+    mut pause_menu_page: ResMut<PauseMenuPage>,
+    mut toggle_pause_event_writer: EventWriter<TogglePause>,
+) {
+    if action_state.just_pressed(Action::Back) {
+        if *pause_menu_page == PauseMenuPage::Main {
+            toggle_pause_event_writer.send(TogglePause);
+        } else {
+            *pause_menu_page = PauseMenuPage::Main;
+        }
+    }
+}
+
+/// Pauses through [`TogglePause`] like the player would, so a recorded
+/// replay pauses at the same tick.
+pub fn pause_on_focus_lost(
+    mut window_focused_event_reader: EventReader<WindowFocused>,
+    mut toggle_pause_event_writer: EventWriter<TogglePause>,
+) {
+    if window_focused_event_reader
+        .iter()
+        .any(|window_focused| !window_focused.focused)
+    {
+        toggle_pause_event_writer.send(TogglePause);
+    }
+}
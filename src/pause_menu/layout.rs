@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+
+use crate::game::audio::AudioSettings;
+use crate::game::mode::Difficulty;
+use crate::game::SimulationState;
+use crate::settings::spawn_audio_settings;
+use crate::ui::{
+    body_text_style, menu_style, remember_focus, spawn_button, spawn_small_button,
+    title_text_style, Focusable, Focused,
+};
+use crate::AppState;
+
+use super::{PauseMenu, PauseMenuButton, PauseMenuPage};
+
+/// Laid over the game, which stays visible underneath.
+pub const PAUSE_MENU_BACKGROUND_COLOR: Color = Color::rgba(0., 0., 0., 0.6);
+
+/// Replaces whatever page is shown with the current one, or only removes it
+/// when the game is not paused.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_pause_menu(
+    mut commands: Commands,
+    pause_menu_query: Query<Entity, With<PauseMenu>>,
+    focused_query: Query<&Focusable, With<Focused>>,
+    pause_menu_page: Res<PauseMenuPage>,
+    app_state: Res<State<AppState>>,
+    simulation_state: Res<State<SimulationState>>,
+    difficulty: Res<Difficulty>,
+    audio_settings: Res<AudioSettings>,
+) {
+    if !pause_menu_page.is_changed() && !pause_menu_query.is_empty() {
+        remember_focus(&mut commands, &focused_query);
+    }
+    for pause_menu_entity in pause_menu_query.iter() {
+        commands.entity(pause_menu_entity).despawn_recursive();
+    }
+    if app_state.get() != &AppState::Game || simulation_state.get() != &SimulationState::Paused {
+        return;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: menu_style(),
+                background_color: PAUSE_MENU_BACKGROUND_COLOR.into(),
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            PauseMenu,
+        ))
+        .with_children(|parent| match *pause_menu_page {
+            PauseMenuPage::Main => {
+                parent.spawn(TextBundle::from_section("Paused", title_text_style()));
+                spawn_button(parent, 0, "Resume", PauseMenuButton::Resume);
+                spawn_button(parent, 1, "Restart", PauseMenuButton::Restart);
+                spawn_button(parent, 2, "Settings", PauseMenuButton::Settings);
+                spawn_button(parent, 3, "Quit to Menu", PauseMenuButton::QuitToMenu);
+            }
+            PauseMenuPage::Settings => {
+                parent.spawn(TextBundle::from_section("Settings", title_text_style()));
+                // The game's score is kept under the difficulty it started
+                // with, so it can only be changed from the main menu.
+                parent.spawn(TextBundle::from_section(
+                    format!("Difficulty: {}", *difficulty),
+                    body_text_style(),
+                ));
+                let index = spawn_audio_settings(parent, 0, &audio_settings);
+                spawn_small_button(parent, index, "Back", PauseMenuButton::Back);
+            }
+        });
+}
@@ -0,0 +1,68 @@
+mod interactions;
+mod layout;
+
+use bevy::prelude::*;
+
+use crate::game::audio::AudioSettings;
+use crate::game::replay::ReplayPlayback;
+use crate::game::SimulationState;
+use crate::AppState;
+
+use interactions::{go_back, handle_pause_menu_buttons, pause_on_focus_lost};
+use layout::spawn_pause_menu;
+
+/// Dims the game and offers a menu whenever the simulation is paused.
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenuPage>()
+            .add_systems(OnEnter(SimulationState::Paused), (show_main_page,))
+            .add_systems(
+                Update,
+                (
+                    (handle_pause_menu_buttons, go_back)
+                        .run_if(in_state(AppState::Game))
+                        .run_if(in_state(SimulationState::Paused)),
+                    spawn_pause_menu.run_if(
+                        state_changed::<AppState>()
+                            .or_else(state_changed::<SimulationState>())
+                            .or_else(resource_changed::<PauseMenuPage>())
+                            .or_else(resource_changed::<AudioSettings>()),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                pause_on_focus_lost
+                    .run_if(not(resource_exists::<ReplayPlayback>()))
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(SimulationState::Running)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct PauseMenu;
+
+/// Which page of the pause menu is shown.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMenuPage {
+    #[default]
+    Main,
+    Settings,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMenuButton {
+    Resume,
+    Restart,
+    Settings,
+    QuitToMenu,
+    Back,
+}
+
+pub fn show_main_page(mut pause_menu_page: ResMut<PauseMenuPage>) {
+    *pause_menu_page = PauseMenuPage::Main;
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::game::audio::{next_volume, AudioSettings};
+use crate::ui::{spawn_small_button, ButtonActivated};
+
+/// Settings buttons shared by the main menu and the pause menu. The menus
+/// lay them out and rebuild themselves when [`AudioSettings`] changes.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_settings_buttons,));
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsButton {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Mute,
+}
+
+/// Spawns the audio settings buttons, focusable from `first_index` on, and
+/// returns the index after the last one.
+pub fn spawn_audio_settings(
+    parent: &mut ChildBuilder,
+    first_index: usize,
+    audio_settings: &AudioSettings,
+) -> usize {
+    let buttons = [
+        (
+            format!("Master Volume: {}", percent(audio_settings.master_volume)),
+            SettingsButton::MasterVolume,
+        ),
+        (
+            format!("Music Volume: {}", percent(audio_settings.music_volume)),
+            SettingsButton::MusicVolume,
+        ),
+        (
+            format!("Sound Volume: {}", percent(audio_settings.sfx_volume)),
+            SettingsButton::SfxVolume,
+        ),
+        (
+            format!("Mute: {}", if audio_settings.muted { "On" } else { "Off" }),
+            SettingsButton::Mute,
+        ),
+    ];
+    let count = buttons.len();
+    for (offset, (label, button)) in buttons.into_iter().enumerate() {
+        spawn_small_button(parent, first_index + offset, label, button);
+    }
+    first_index + count
+}
+
+fn percent(volume: f32) -> String {
+    format!("{:.0}%", volume * 100.)
+}
+
+pub fn handle_settings_buttons(
+    mut button_activated_event_reader: EventReader<ButtonActivated>,
+    button_query: Query<&SettingsButton>,
+    mut audio_settings: ResMut<AudioSettings>,
+) {
+    for event in button_activated_event_reader.iter() {
+        match button_query.get(event.entity) {
+            Ok(SettingsButton::MasterVolume) => {
+                audio_settings.master_volume = next_volume(audio_settings.master_volume)
+            }
+            Ok(SettingsButton::MusicVolume) => {
+                audio_settings.music_volume = next_volume(audio_settings.music_volume)
+            }
+            Ok(SettingsButton::SfxVolume) => {
+                audio_settings.sfx_volume = next_volume(audio_settings.sfx_volume)
+            }
+            Ok(SettingsButton::Mute) => audio_settings.muted = !audio_settings.muted,
+            Err(_) => {}
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::math::{IVec2, Vec2};
+use bevy::utils::HashMap;
+
+/// Uniform grid bucketing items by the cell their center falls in, so that
+/// only items in the same or neighbouring cells are tested against each other.
+///
+/// Items bigger than a cell still work, the search just reaches further out.
+/// It is fastest with a cell size around the size of the biggest item.
+pub struct SpatialHash<T> {
+    cell_size: f32,
+    max_half_extents: Vec2,
+    cells: HashMap<IVec2, Vec<Entry<T>>>,
+}
+
+struct Entry<T> {
+    item: T,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl<T: Copy + Ord> SpatialHash<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            max_half_extents: Vec2::ZERO,
+            cells: HashMap::default(),
+        }
+    }
+
+    /// Removes every item, keeping the allocated cells around for reuse.
+    pub fn clear(&mut self) {
+        self.max_half_extents = Vec2::ZERO;
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+    }
+
+    pub fn insert(&mut self, item: T, center: Vec2, half_extents: Vec2) {
+        self.max_half_extents = self.max_half_extents.max(half_extents);
+        self.cells
+            .entry(self.cell(center))
+            .or_default()
+            .push(Entry {
+                item,
+                min: center - half_extents,
+                max: center + half_extents,
+            });
+    }
+
+    /// Items whose bounding box overlaps the given box.
+    pub fn query(&self, center: Vec2, half_extents: Vec2) -> Vec<T> {
+        let (min, max) = (center - half_extents, center + half_extents);
+        let min_cell = self.cell(min - self.max_half_extents);
+        let max_cell = self.cell(max + self.max_half_extents);
+
+        let mut items = Vec::new();
+        for x in min_cell.x..=max_cell.x {
+            for y in min_cell.y..=max_cell.y {
+                if let Some(entries) = self.cells.get(&IVec2::new(x, y)) {
+                    items.extend(
+                        entries
+                            .iter()
+                            .filter(|entry| overlaps(entry.min, entry.max, min, max))
+                            .map(|entry| entry.item),
+                    );
+                }
+            }
+        }
+        items
+    }
+
+    /// Every pair of items whose bounding boxes overlap, each pair once with
+    /// the smaller item first.
+    pub fn candidate_pairs(&self) -> Vec<(T, T)> {
+        // How many cells apart two centers can be while their boxes overlap.
+        let reach = (self.max_half_extents * 2. / self.cell_size)
+            .ceil()
+            .as_ivec2();
+
+        let mut pairs = Vec::new();
+        for (&cell, entries) in self.cells.iter() {
+            for (index, entry) in entries.iter().enumerate() {
+                push_overlapping(&mut pairs, entry, &entries[index + 1..]);
+            }
+            // Only look at the neighbours above and to the right, the others
+            // pair up with this cell when it is their turn.
+            for x in -reach.x..=reach.x {
+                for y in 0..=reach.y {
+                    if y == 0 && x <= 0 {
+                        continue;
+                    }
+                    if let Some(other_entries) = self.cells.get(&(cell + IVec2::new(x, y))) {
+                        for entry in entries {
+                            push_overlapping(&mut pairs, entry, other_entries);
+                        }
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    fn cell(&self, point: Vec2) -> IVec2 {
+        (point / self.cell_size).floor().as_ivec2()
+    }
+}
+
+fn push_overlapping<T: Copy + Ord>(
+    pairs: &mut Vec<(T, T)>,
+    entry: &Entry<T>,
+    other_entries: &[Entry<T>],
+) {
+    for other_entry in other_entries {
+        if overlaps(entry.min, entry.max, other_entry.min, other_entry.max) {
+            pairs.push(if entry.item < other_entry.item {
+                (entry.item, other_entry.item)
+            } else {
+                (other_entry.item, entry.item)
+            });
+        }
+    }
+}
+
+fn overlaps(min: Vec2, max: Vec2, other_min: Vec2, other_max: Vec2) -> bool {
+    // Non-short-circuiting, the branches are too unpredictable to pay off.
+    (min.x <= other_max.x)
+        & (other_min.x <= max.x)
+        & (min.y <= other_max.y)
+        & (other_min.y <= max.y)
+}
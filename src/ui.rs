@@ -0,0 +1,305 @@
+use bevy::prelude::*;
+
+use crate::game::actions::{Action, ActionState};
+
+pub const NORMAL_BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+pub const FOCUSED_BUTTON_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+pub const PRESSED_BUTTON_COLOR: Color = Color::rgb(0.35, 0.75, 0.35);
+pub const TEXT_COLOR: Color = Color::WHITE;
+
+/// Buttons shared by every menu: hover and pressed colors, and moving the
+/// focus between them with the move, confirm and back actions. Menus react to
+/// [`ButtonActivated`] rather than to clicks, so all devices work the same.
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ButtonActivated>().add_systems(
+            Update,
+            (
+                focus_first_button,
+                focus_hovered_button,
+                navigate_focus,
+                activate_buttons,
+                update_button_colors,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// A button that can be focused, in the order of `index` within its menu.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Focusable {
+    pub index: usize,
+}
+
+/// The one button that confirming activates.
+#[derive(Component, Debug)]
+pub struct Focused;
+
+/// Focus to give back once a rebuilt menu has its buttons.
+#[derive(Resource, Debug)]
+pub struct RestoreFocus {
+    pub index: usize,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ButtonActivated {
+    pub entity: Entity,
+}
+
+/// Present while the player types into a text field or picks a new binding,
+/// so what they press does not also move the focus or press a button.
+#[derive(Resource, Debug)]
+pub struct TextInput;
+
+pub fn button_style() -> Style {
+    Style {
+        width: Val::Px(240.),
+        height: Val::Px(64.),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+/// For menus with many rows. Grows with its label.
+pub fn small_button_style() -> Style {
+    Style {
+        min_width: Val::Px(240.),
+        height: Val::Px(40.),
+        padding: UiRect::horizontal(Val::Px(16.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+pub fn menu_style() -> Style {
+    Style {
+        width: Val::Percent(100.),
+        height: Val::Percent(100.),
+        flex_direction: FlexDirection::Column,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        row_gap: Val::Px(12.),
+        ..default()
+    }
+}
+
+pub fn title_text_style() -> TextStyle {
+    TextStyle {
+        font_size: 64.,
+        color: TEXT_COLOR,
+        ..default()
+    }
+}
+
+pub fn button_text_style() -> TextStyle {
+    TextStyle {
+        font_size: 32.,
+        color: TEXT_COLOR,
+        ..default()
+    }
+}
+
+pub fn body_text_style() -> TextStyle {
+    TextStyle {
+        font_size: 24.,
+        color: TEXT_COLOR,
+        ..default()
+    }
+}
+
+/// Spawns a focusable button with a text label under `parent`, tagged with
+/// `action` so the menu knows what it does.
+pub fn spawn_button(
+    parent: &mut ChildBuilder,
+    index: usize,
+    label: impl Into<String>,
+    action: impl Component,
+) -> Entity {
+    spawn_styled_button(
+        parent,
+        index,
+        label,
+        button_style(),
+        button_text_style(),
+        action,
+    )
+}
+
+pub fn spawn_small_button(
+    parent: &mut ChildBuilder,
+    index: usize,
+    label: impl Into<String>,
+    action: impl Component,
+) -> Entity {
+    spawn_styled_button(
+        parent,
+        index,
+        label,
+        small_button_style(),
+        body_text_style(),
+        action,
+    )
+}
+
+pub fn spawn_styled_button(
+    parent: &mut ChildBuilder,
+    index: usize,
+    label: impl Into<String>,
+    style: Style,
+    text_style: TextStyle,
+    action: impl Component,
+) -> Entity {
+    parent
+        .spawn((
+            ButtonBundle {
+                style,
+                background_color: NORMAL_BUTTON_COLOR.into(),
+                ..default()
+            },
+            Focusable { index },
+            action,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, text_style));
+        })
+        .id()
+}
+
+/// Keeps one button focused whenever there are any, so a keyboard or
+/// gamepad can be used straight away. A menu rebuilt in place gets the focus
+/// back where [`RestoreFocus`] says.
+pub fn focus_first_button(
+    mut commands: Commands,
+    restore_focus: Option<Res<RestoreFocus>>,
+    focusable_query: Query<(Entity, &Focusable)>,
+    focused_query: Query<(), With<Focused>>,
+) {
+    if !focused_query.is_empty() || focusable_query.is_empty() {
+        return;
+    }
+
+    let restored = restore_focus.and_then(|restore_focus| {
+        focusable_query
+            .iter()
+            .find(|(_, focusable)| focusable.index == restore_focus.index)
+    });
+    if let Some((entity, _)) = restored.or_else(|| {
+        focusable_query
+            .iter()
+            .min_by_key(|(_, focusable)| focusable.index)
+    }) {
+        commands.entity(entity).insert(Focused);
+    }
+    commands.remove_resource::<RestoreFocus>();
+}
+
+/// Remembers the focused button of a menu about to be rebuilt, so the new
+/// button with the same index takes the focus.
+pub fn remember_focus(commands: &mut Commands, focused_query: &Query<&Focusable, With<Focused>>) {
+    if let Ok(focusable) = focused_query.get_single() {
+        commands.insert_resource(RestoreFocus {
+            index: focusable.index,
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn focus_hovered_button(
+    mut commands: Commands,
+    interaction_query: Query<(Entity, &Interaction), (Changed<Interaction>, With<Focusable>)>,
+    focused_query: Query<Entity, With<Focused>>,
+) {
+    for (entity, interaction) in interaction_query.iter() {
+        if *interaction == Interaction::Hovered {
+            for focused in focused_query.iter() {
+                commands.entity(focused).remove::<Focused>();
+            }
+            commands.entity(entity).insert(Focused);
+        }
+    }
+}
+
+pub fn navigate_focus(
+    mut commands: Commands,
+    action_state: Res<ActionState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    text_input: Option<Res<TextInput>>,
+    focusable_query: Query<(Entity, &Focusable)>,
+    focused_query: Query<Entity, With<Focused>>,
+) {
+    if text_input.is_some() {
+        return;
+    }
+
+    let mut step = 0;
+    if action_state.just_pressed(Action::MoveUp) {
+        step -= 1;
+    }
+    if action_state.just_pressed(Action::MoveDown) || keyboard_input.just_pressed(KeyCode::Tab) {
+        step += 1;
+    }
+
+    if step == 0 {
+        return;
+    }
+
+    let mut focusables: Vec<_> = focusable_query.iter().collect();
+    if focusables.is_empty() {
+        return;
+    }
+    focusables.sort_by_key(|(_, focusable)| focusable.index);
+
+    let current = focused_query
+        .get_single()
+        .ok()
+        .and_then(|focused| focusables.iter().position(|(entity, _)| *entity == focused))
+        .unwrap_or(0);
+    let next = (current as i32 + step).rem_euclid(focusables.len() as i32) as usize;
+
+    for focused in focused_query.iter() {
+        commands.entity(focused).remove::<Focused>();
+    }
+    commands.entity(focusables[next].0).insert(Focused);
+}
+
+/// Activates a button when it is clicked, or when it has the focus and
+/// confirm is pressed.
+#[allow(clippy::type_complexity)]
+pub fn activate_buttons(
+    action_state: Res<ActionState>,
+    text_input: Option<Res<TextInput>>,
+    interaction_query: Query<(Entity, &Interaction), (Changed<Interaction>, With<Focusable>)>,
+    focused_query: Query<Entity, With<Focused>>,
+    mut button_activated_event_writer: EventWriter<ButtonActivated>,
+) {
+    for (entity, interaction) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            button_activated_event_writer.send(ButtonActivated { entity });
+        }
+    }
+
+    let confirmed = text_input.is_none() && action_state.just_pressed(Action::Confirm);
+    if confirmed {
+        if let Ok(entity) = focused_query.get_single() {
+            button_activated_event_writer.send(ButtonActivated { entity });
+        }
+    }
+}
+
+pub fn update_button_colors(
+    mut button_query: Query<(&Interaction, Option<&Focused>, &mut BackgroundColor), With<Button>>,
+) {
+    for (interaction, focused, mut background_color) in button_query.iter_mut() {
+        *background_color = match (interaction, focused) {
+            (Interaction::Pressed, _) => PRESSED_BUTTON_COLOR,
+            (Interaction::Hovered, _) | (_, Some(_)) => FOCUSED_BUTTON_COLOR,
+            (Interaction::None, None) => NORMAL_BUTTON_COLOR,
+        }
+        .into();
+    }
+}